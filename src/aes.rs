@@ -77,6 +77,20 @@ pub fn cbc_mac(message: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
         .to_vec()
 }
 
+/// Encrypts or decrypts a byte slice using AES-128 in CTR mode. The keystream blocks are
+/// AES(nonce || counter), with a 64-bit little endian nonce and block counter.
+pub fn aes128_ctr(input: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    input
+        .chunks(16)
+        .enumerate()
+        .flat_map(|(counter, chunk)| {
+            let block = [nonce.to_le_bytes(), (counter as u64).to_le_bytes()].concat();
+            let keystream = aes128_encrypt_block(&block, key);
+            xor_block(chunk, &keystream[..chunk.len()])
+        })
+        .collect()
+}
+
 mod test {
     #[allow(unused_imports)]
     use super::*;
@@ -110,4 +124,20 @@ mod test {
 
         assert_eq!(plaintext, pkcs7_unpad(plaintext2.as_slice()).unwrap());
     }
+
+    #[test]
+    fn test_aes128_ctr() {
+        use base64::Engine;
+        let ciphertext = base64::engine::general_purpose::STANDARD
+            .decode("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==")
+            .unwrap();
+        let key = "YELLOW SUBMARINE".as_bytes();
+
+        let plaintext = aes128_ctr(&ciphertext, key, 0);
+        assert_eq!(
+            plaintext,
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ".as_bytes()
+        );
+        assert_eq!(aes128_ctr(&plaintext, key, 0), ciphertext);
+    }
 }
@@ -0,0 +1,276 @@
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+/// Small primes used to cheaply weed out composites before running Miller-Rabin.
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Returns `base^exp mod m`.
+pub fn modexp(base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+    base.modpow(exp, m)
+}
+
+/// Runs the extended Euclidean algorithm on `a` and `b`, returning `(g, x, y)` such that
+/// `a*x + b*y = g = gcd(a, b)`.
+pub fn egcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+    let (mut old_t, mut t) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        (old_r, r) = (r.clone(), &old_r - &q * &r);
+        (old_s, s) = (s.clone(), &old_s - &q * &s);
+        (old_t, t) = (t.clone(), &old_t - &q * &t);
+    }
+
+    (old_r, old_s, old_t)
+}
+
+/// Returns the inverse of `a` modulo `m`, or `None` if `a` and `m` are not coprime.
+pub fn modinv(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    let m = BigInt::from(m.clone());
+    let (g, x, _) = egcd(&BigInt::from(a.clone()), &m);
+    if !g.is_one() {
+        return None;
+    }
+
+    x.mod_floor(&m).to_biguint()
+}
+
+/// Returns the integer k-th root of `n`, i.e., the largest `r` such that `r^k <= n`.
+pub fn nth_root(n: &BigUint, k: u32) -> BigUint {
+    assert!(k > 0);
+    if n.is_zero() || k == 1 {
+        return n.clone();
+    }
+
+    // Newton's method, starting from a power of two that's guaranteed to be >= the root. The
+    // iterates decrease monotonically until they reach the floor of the root.
+    let k_big = BigUint::from(k);
+    let mut x = BigUint::one() << n.bits().div_ceil(k as u64);
+    loop {
+        let y = (&x * (k - 1) + n / x.pow(k - 1)) / &k_big;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Returns the k-th root of `n` if `n` is a perfect k-th power, or `None` otherwise.
+pub fn exact_nth_root(n: &BigUint, k: u32) -> Option<BigUint> {
+    let root = nth_root(n, k);
+    if &root.pow(k) == n {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// Returns the integer cube root of `n`.
+pub fn cube_root(n: &BigUint) -> BigUint {
+    nth_root(n, 3)
+}
+
+/// Solves the system `x = residues[i] mod moduli[i]` using the Chinese Remainder Theorem,
+/// returning `x` modulo the product of the moduli. Returns `None` if the moduli are not
+/// pairwise coprime.
+pub fn crt(residues: &[BigUint], moduli: &[BigUint]) -> Option<BigUint> {
+    assert_eq!(residues.len(), moduli.len());
+    let product: BigUint = moduli.iter().product();
+
+    let mut result = BigUint::zero();
+    for (r, m) in residues.iter().zip(moduli.iter()) {
+        let ms = &product / m;
+        result += r * &ms * modinv(&ms, m)?;
+    }
+
+    Some(result % product)
+}
+
+/// Returns true if `n` is probably prime, using trial division by small primes followed by
+/// `rounds` rounds of Miller-Rabin with random bases.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let two = BigUint::from(2u32);
+    if n < &two {
+        return false;
+    }
+
+    for p in SMALL_PRIMES {
+        if n == &BigUint::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+
+    // Write n - 1 as 2^s * d, with d odd.
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> s;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = modexp(&a, &d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+
+        for _ in 1..s {
+            x = modexp(&x, &two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+/// Generates a random prime with exactly `bits` bits.
+pub fn gen_prime(bits: u64) -> BigUint {
+    assert!(bits >= 2);
+    let mut rng = rand::thread_rng();
+    loop {
+        // Set the top bit so the prime has the right size, and the bottom bit so it's odd.
+        let mut candidate = rng.gen_biguint(bits);
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate, 40) {
+            return candidate;
+        }
+    }
+}
+
+/// Returns the unsigned big-endian integer represented by `bytes`.
+pub fn from_bytes(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+/// Returns the big-endian byte representation of `n`, with no leading zeros.
+pub fn to_bytes(n: &BigUint) -> Vec<u8> {
+    n.to_bytes_be()
+}
+
+/// Returns the big-endian byte representation of `n`, left-padded with zeros to `len` bytes.
+pub fn to_bytes_padded(n: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = n.to_bytes_be();
+    assert!(bytes.len() <= len);
+    [vec![0u8; len - bytes.len()], bytes].concat()
+}
+
+/// Parses a hex string (whitespace is ignored) into an unsigned integer.
+pub fn from_hex(hex: &str) -> BigUint {
+    let hex = hex.split_whitespace().collect::<String>();
+    BigUint::parse_bytes(hex.as_bytes(), 16).unwrap()
+}
+
+/// Returns the lowercase hex representation of `n`.
+pub fn to_hex(n: &BigUint) -> String {
+    n.to_str_radix(16)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_modexp() {
+        let result = modexp(
+            &BigUint::from(4u32),
+            &BigUint::from(13u32),
+            &BigUint::from(497u32),
+        );
+        assert_eq!(result, BigUint::from(445u32));
+    }
+
+    #[test]
+    fn test_modinv() {
+        let inv = modinv(&BigUint::from(17u32), &BigUint::from(3120u32)).unwrap();
+        assert_eq!(inv, BigUint::from(2753u32));
+        assert!(modinv(&BigUint::from(6u32), &BigUint::from(9u32)).is_none());
+
+        let p = gen_prime(128);
+        let a = rand::thread_rng().gen_biguint_below(&p);
+        if !a.is_zero() {
+            assert!((&a * modinv(&a, &p).unwrap() % &p).is_one());
+        }
+    }
+
+    #[test]
+    fn test_nth_root() {
+        assert_eq!(cube_root(&BigUint::from(27u32)), BigUint::from(3u32));
+        assert_eq!(cube_root(&BigUint::from(26u32)), BigUint::from(2u32));
+        assert_eq!(
+            nth_root(&BigUint::from(1u32 << 20), 5),
+            BigUint::from(16u32)
+        );
+        assert_eq!(nth_root(&BigUint::zero(), 3), BigUint::zero());
+        assert_eq!(nth_root(&BigUint::one(), 7), BigUint::one());
+        assert_eq!(
+            nth_root(&BigUint::from(12345u32), 1),
+            BigUint::from(12345u32)
+        );
+
+        // The root is always the floor: r^k <= n < (r + 1)^k.
+        let mut rng = rand::thread_rng();
+        for k in 2..8 {
+            for bits in [8, 64, 1024, 3000] {
+                let n = rng.gen_biguint(bits);
+                let r = nth_root(&n, k);
+                assert!(r.pow(k) <= n);
+                assert!((&r + 1u32).pow(k) > n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_nth_root() {
+        let mut rng = rand::thread_rng();
+        for k in 2..8 {
+            let r = rng.gen_biguint(500);
+            assert_eq!(exact_nth_root(&r.pow(k), k), Some(r.clone()));
+            assert_eq!(exact_nth_root(&(r.pow(k) + 1u32), k), None);
+        }
+    }
+
+    #[test]
+    fn test_crt() {
+        let residues = [2u32, 3, 2].map(BigUint::from);
+        let moduli = [3u32, 5, 7].map(BigUint::from);
+        assert_eq!(crt(&residues, &moduli).unwrap(), BigUint::from(23u32));
+
+        let moduli = [4u32, 6, 7].map(BigUint::from);
+        assert!(crt(&residues, &moduli).is_none());
+    }
+
+    #[test]
+    fn test_primes() {
+        assert!(is_probable_prime(&BigUint::from(2u32), 10));
+        assert!(is_probable_prime(&BigUint::from(7919u32), 10));
+        assert!(!is_probable_prime(&BigUint::from(7917u32), 10));
+
+        // Carmichael number: fools the Fermat test, but not Miller-Rabin.
+        assert!(!is_probable_prime(&BigUint::from(561u32), 10));
+
+        let p = gen_prime(256);
+        assert_eq!(p.bits(), 256);
+        assert!(is_probable_prime(&p, 10));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let n = from_hex("deadbeef 0102");
+        assert_eq!(to_hex(&n), "deadbeef0102");
+        assert_eq!(to_bytes(&n), vec![0xde, 0xad, 0xbe, 0xef, 0x01, 0x02]);
+        assert_eq!(from_bytes(&to_bytes(&n)), n);
+        assert_eq!(to_bytes_padded(&BigUint::from(1u32), 3), vec![0, 0, 1]);
+    }
+}
@@ -0,0 +1,116 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::One;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::bignum::*;
+
+/// The 1536-bit MODP group prime from RFC 3526, as used by the cryptopals challenges.
+const NIST_P: &str = r"
+    ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74
+    020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f1437
+    4fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed
+    ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf05
+    98da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb
+    9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff
+";
+
+/// Diffie-Hellman group parameters: a prime modulus `p` and a generator `g`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhGroup {
+    pub p: BigUint,
+    pub g: BigUint,
+}
+
+/// A Diffie-Hellman keypair, where `public = g^private mod p`.
+#[derive(Clone, Debug)]
+pub struct DhKeypair {
+    pub private: BigUint,
+    pub public: BigUint,
+}
+
+/// Symmetric keys derived from a Diffie-Hellman shared secret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionKeys {
+    /// AES-128 key: the first 16 bytes of SHA1(secret).
+    pub enc_key: Vec<u8>,
+    /// MAC key: SHA256(secret).
+    pub mac_key: Vec<u8>,
+}
+
+impl DhGroup {
+    pub fn new(p: BigUint, g: BigUint) -> Self {
+        Self { p, g }
+    }
+
+    /// The RFC 3526 1536-bit MODP group with generator 2.
+    pub fn nist() -> Self {
+        Self::new(from_hex(NIST_P), BigUint::from(2u32))
+    }
+
+    /// A tiny toy group (p = 37, g = 5) that's handy for checking the math by hand.
+    pub fn toy() -> Self {
+        Self::new(BigUint::from(37u32), BigUint::from(5u32))
+    }
+
+    /// A 64-bit toy group. p = 0xfffffffffffffa43 is the largest safe prime below 2^64, so
+    /// p = 2q + 1 with q prime, and g = 4 generates the subgroup of order q.
+    pub fn toy64() -> Self {
+        Self::new(BigUint::from(0xfffffffffffffa43u64), BigUint::from(4u32))
+    }
+
+    /// Generates a keypair with a random private key in `[1, p)`.
+    pub fn generate_keypair(&self) -> DhKeypair {
+        let private = rand::thread_rng().gen_biguint_range(&BigUint::one(), &self.p);
+        let public = modexp(&self.g, &private, &self.p);
+        DhKeypair { private, public }
+    }
+
+    /// Returns `other_public^private mod p`.
+    pub fn shared_secret(&self, keypair: &DhKeypair, other_public: &BigUint) -> BigUint {
+        modexp(other_public, &keypair.private, &self.p)
+    }
+}
+
+/// Derives an AES-128 key and a MAC key from a shared secret. The secret is hashed as its
+/// minimal big-endian byte representation.
+pub fn derive_keys(secret: &BigUint) -> SessionKeys {
+    let secret = to_bytes(secret);
+    SessionKeys {
+        enc_key: Sha1::digest(&secret)[..16].to_vec(),
+        mac_key: Sha256::digest(&secret).to_vec(),
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_shared_secret() {
+        for group in [DhGroup::toy(), DhGroup::toy64(), DhGroup::nist()] {
+            let a = group.generate_keypair();
+            let b = group.generate_keypair();
+            let s1 = group.shared_secret(&a, &b.public);
+            let s2 = group.shared_secret(&b, &a.public);
+            assert_eq!(s1, s2);
+            assert_eq!(derive_keys(&s1), derive_keys(&s2));
+        }
+    }
+
+    #[test]
+    fn test_toy64_subgroup() {
+        let group = DhGroup::toy64();
+        let q = (&group.p - 1u32) >> 1;
+        assert!(is_probable_prime(&group.p, 20) && is_probable_prime(&q, 20));
+        assert_eq!(modexp(&group.g, &q, &group.p), BigUint::one());
+    }
+
+    #[test]
+    fn test_derive_keys() {
+        let keys = derive_keys(&BigUint::from(0u32));
+        assert_eq!(keys.enc_key.len(), 16);
+        assert_eq!(keys.mac_key.len(), 32);
+        assert_ne!(keys, derive_keys(&BigUint::from(1u32)));
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::bail;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use sha1::{Digest, Sha1};
+
+use crate::bignum::*;
+
+const CRYPTOPALS_P: &str = r"
+    800000000000000089e1855218a0e7dac38136ffafa72eda7859f2171e25e65e
+    ac698c1702578b07dc2a1076da241c76c62d374d8389ea5aeffd3226a0530cc5
+    65f3bf6b50929139ebeac04f48c3c84afb796d61e5a4f9a8fda812ab59494232
+    c7d2b4deb50aa18ee9e132bfa85ac4374d7f9091abc3d015efc871a584471bb1
+";
+
+const CRYPTOPALS_G: &str = r"
+    5958c9d3898b224b12672c0b98e06c60df923cb8bc999d119458fef538b8fa40
+    46c8db53039db620c094c9fa077ef389b5322a559946a71903f990f1f7e0e025
+    e2d7f7cf494aff1a0470f5b64c36b625a097f1651fe775323556fe00b3608c88
+    7892878480e99041be601a62166ca6894bdd41a7054ec89f756ba9fc95302291
+";
+
+/// DSA domain parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DsaParams {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+/// A DSA public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DsaPublicKey {
+    pub params: DsaParams,
+    pub y: BigUint,
+}
+
+/// A DSA private key, where `y = g^x mod p`.
+#[derive(Clone, Debug)]
+pub struct DsaPrivateKey {
+    pub params: DsaParams,
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DsaSignature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+impl DsaParams {
+    /// The 1024-bit parameters used by the cryptopals DSA challenges.
+    pub fn cryptopals() -> Self {
+        Self {
+            p: from_hex(CRYPTOPALS_P),
+            q: from_hex("f4f47f05794b256174bba6e9b396a7707e563c5b"),
+            g: from_hex(CRYPTOPALS_G),
+        }
+    }
+
+    /// Checks that q divides p - 1, and that g generates the subgroup of order q. Verifiers
+    /// that take parameters from untrusted sources must call this, since degenerate generators
+    /// like 0 or p + 1 make it easy to forge signatures.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let DsaParams { p, q, g } = self;
+        if !((p - 1u32) % q).is_zero() {
+            bail!("invalid parameters: q does not divide p - 1");
+        }
+        if g <= &BigUint::one() || g >= p {
+            bail!("invalid parameters: g out of range");
+        }
+        if !modexp(g, q, p).is_one() {
+            bail!("invalid parameters: g does not have order q");
+        }
+        Ok(())
+    }
+}
+
+/// Returns SHA1(message) as an integer.
+pub fn hash_message(message: &[u8]) -> BigUint {
+    from_bytes(&Sha1::digest(message))
+}
+
+/// Recovers the private key from a signature of a message with hash `h`, given the nonce `k`
+/// that was used to create it:
+///
+///   x = (s * k - H(m)) / r mod q
+pub fn recover_x(
+    params: &DsaParams,
+    h: &BigUint,
+    sig: &DsaSignature,
+    k: &BigUint,
+) -> Option<BigUint> {
+    let q = &params.q;
+    let sk = (&sig.s * k) % q;
+    let numerator = (sk + q - h % q) % q;
+    Some(numerator * modinv(&sig.r, q)? % q)
+}
+
+/// Recovers the nonce shared by two signatures over messages with hashes `m1` and `m2`. Since r
+/// only depends on k, signatures with the same r share a nonce, and:
+///
+///   k = (m1 - m2) / (s1 - s2) mod q
+///
+/// Returns `None` if the signatures don't share a nonce, or if s1 = s2.
+pub fn recover_k_from_repeated_nonce(
+    params: &DsaParams,
+    (m1, sig1): (&BigUint, &DsaSignature),
+    (m2, sig2): (&BigUint, &DsaSignature),
+) -> Option<BigUint> {
+    let q = &params.q;
+    if sig1.r != sig2.r {
+        return None;
+    }
+
+    let dm = (m1 % q + q - m2 % q) % q;
+    let ds = (&sig1.s % q + q - &sig2.s % q) % q;
+    Some(dm * modinv(&ds, q)? % q)
+}
+
+impl DsaPublicKey {
+    /// Verifies a signature over SHA1(message), after checking the domain parameters and that
+    /// r and s are in range.
+    pub fn verify(&self, message: &[u8], sig: &DsaSignature) -> bool {
+        let q = &self.params.q;
+        if self.params.validate().is_err() {
+            return false;
+        }
+        if sig.r.is_zero() || &sig.r >= q || sig.s.is_zero() || &sig.s >= q {
+            return false;
+        }
+
+        self.verify_unchecked(message, sig)
+    }
+
+    /// Verifies a signature over SHA1(message) without checking the domain parameters or the
+    /// range of r and s. Only use this to demonstrate why those checks matter.
+    pub fn verify_unchecked(&self, message: &[u8], sig: &DsaSignature) -> bool {
+        let DsaParams { p, q, g } = &self.params;
+        let Some(w) = modinv(&sig.s, q) else {
+            return false;
+        };
+        let u1 = hash_message(message) * &w % q;
+        let u2 = &sig.r * &w % q;
+        let v = modexp(g, &u1, p) * modexp(&self.y, &u2, p) % p % q;
+
+        v == sig.r
+    }
+}
+
+impl DsaPrivateKey {
+    /// Generates a random keypair with the given parameters.
+    pub fn generate(params: DsaParams) -> Self {
+        let x = rand::thread_rng().gen_biguint_range(&BigUint::one(), &params.q);
+        Self::from_x(params, x)
+    }
+
+    pub fn from_x(params: DsaParams, x: BigUint) -> Self {
+        let y = modexp(&params.g, &x, &params.p);
+        Self { params, x, y }
+    }
+
+    pub fn public_key(&self) -> DsaPublicKey {
+        DsaPublicKey {
+            params: self.params.clone(),
+            y: self.y.clone(),
+        }
+    }
+
+    /// Signs SHA1(message) with a random nonce.
+    pub fn sign(&self, message: &[u8]) -> DsaSignature {
+        let mut rng = rand::thread_rng();
+        loop {
+            let k = rng.gen_biguint_range(&BigUint::one(), &self.params.q);
+            if let Some(sig) = self.sign_with_k(message, &k) {
+                return sig;
+            }
+        }
+    }
+
+    /// Signs SHA1(message) with the nonce `k`, returning `None` if `k` produces a degenerate
+    /// signature (r = 0 or s = 0). Reusing or leaking `k` leaks the private key.
+    pub fn sign_with_k(&self, message: &[u8], k: &BigUint) -> Option<DsaSignature> {
+        let DsaParams { p, q, g } = &self.params;
+        let r = modexp(g, k, p) % q;
+        if r.is_zero() {
+            return None;
+        }
+
+        let s = modinv(k, q)? * (hash_message(message) + &self.x * &r) % q;
+        if s.is_zero() {
+            return None;
+        }
+
+        Some(DsaSignature { r, s })
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let params = DsaParams::cryptopals();
+        assert!(params.validate().is_ok());
+
+        let p = params.p.clone();
+        for g in [BigUint::zero(), BigUint::one(), &p + 1u32, &p - 1u32] {
+            let params = DsaParams {
+                g,
+                ..params.clone()
+            };
+            assert!(params.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let key = DsaPrivateKey::generate(DsaParams::cryptopals());
+        let public = key.public_key();
+
+        let sig = key.sign(b"hello world");
+        assert!(public.verify(b"hello world", &sig));
+        assert!(!public.verify(b"hello world!", &sig));
+
+        let other = DsaPrivateKey::generate(DsaParams::cryptopals()).public_key();
+        assert!(!other.verify(b"hello world", &sig));
+    }
+
+    #[test]
+    fn test_recover_x() {
+        let key = DsaPrivateKey::generate(DsaParams::cryptopals());
+        let k = BigUint::from(12345u32);
+        let sig = key.sign_with_k(b"hello world", &k).unwrap();
+        let x = recover_x(&key.params, &hash_message(b"hello world"), &sig, &k);
+        assert_eq!(x, Some(key.x));
+    }
+
+    #[test]
+    fn test_repeated_nonce() {
+        let key = DsaPrivateKey::generate(DsaParams::cryptopals());
+        let k = rand::thread_rng().gen_biguint_range(&BigUint::one(), &key.params.q);
+        let (m1, m2) = (hash_message(b"first"), hash_message(b"second"));
+        let sig1 = key.sign_with_k(b"first", &k).unwrap();
+        let sig2 = key.sign_with_k(b"second", &k).unwrap();
+
+        let recovered = recover_k_from_repeated_nonce(&key.params, (&m1, &sig1), (&m2, &sig2));
+        assert_eq!(recovered.as_ref(), Some(&k));
+        let x = recover_x(&key.params, &m1, &sig1, &recovered.unwrap());
+        assert_eq!(x, Some(key.x.clone()));
+
+        // Signatures with different nonces have different r, so there's nothing to recover.
+        let sig3 = key.sign(b"third");
+        let m3 = hash_message(b"third");
+        assert!(recover_k_from_repeated_nonce(&key.params, (&m1, &sig1), (&m3, &sig3)).is_none());
+    }
+}
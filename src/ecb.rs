@@ -0,0 +1,174 @@
+use anyhow::{anyhow, bail};
+
+/// Byte-at-a-time ECB decryption. Given an oracle that encrypts `prefix || input || secret`
+/// under ECB with a fixed key, where the prefix is unknown but fixed, this recovers the secret
+/// one byte at a time, by lining each byte up at the end of a block whose other bytes we know.
+pub struct EcbByteAtATime<F: Fn(&[u8]) -> Vec<u8>> {
+    oracle: F,
+    pub block_size: usize,
+    pub prefix_len: usize,
+    pub secret_len: usize,
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> EcbByteAtATime<F> {
+    /// Probes the oracle for the block size, the length of the prefix, and the length of the
+    /// secret, and checks that it really is using ECB.
+    pub fn new(oracle: F) -> anyhow::Result<Self> {
+        // The ciphertext grows by a whole block once the input pushes the padding over the
+        // edge. At that point, prefix || input || secret is exactly the original length.
+        let base_len = oracle(&[]).len();
+        let (input_len, block_size) = (1..=256)
+            .find_map(|n| {
+                let len = oracle(&vec![0; n]).len();
+                (len > base_len).then_some((n, len - base_len))
+            })
+            .ok_or_else(|| anyhow!("ciphertext length never changed"))?;
+
+        let repeated = oracle(&vec![0; 3 * block_size]);
+        let blocks = repeated.chunks(block_size).collect::<Vec<_>>();
+        if !blocks.windows(2).any(|pair| pair[0] == pair[1]) {
+            bail!("oracle doesn't look like it's using ECB");
+        }
+
+        let mut attack = Self {
+            oracle,
+            block_size,
+            prefix_len: 0,
+            secret_len: 0,
+        };
+        attack.prefix_len = attack.detect_prefix_len()?;
+        attack.secret_len = base_len - input_len - attack.prefix_len;
+        Ok(attack)
+    }
+
+    /// Finds the length of the prefix by feeding in filler followed by two sentinel blocks.
+    /// Once the filler pads the prefix out to a block boundary, the sentinels encrypt to two
+    /// equal blocks, and the prefix ends where the filler starts. We try two different sentinels
+    /// and require the same pair of blocks to match under both, and to change between them, so
+    /// repeated blocks in the prefix, or prefix and secret bytes that happen to look like a
+    /// sentinel, can't fool us.
+    fn detect_prefix_len(&self) -> anyhow::Result<usize> {
+        let bs = self.block_size;
+        for n in 0..bs {
+            let probe = |sentinel| {
+                let input = [vec![b'F'; n], vec![sentinel; 2 * bs]].concat();
+                (self.oracle)(&input)
+                    .chunks(bs)
+                    .map(|block| block.to_vec())
+                    .collect::<Vec<_>>()
+            };
+            let (a, b) = (probe(b'S'), probe(b'T'));
+            let aligned =
+                (0..a.len() - 1).find(|&i| a[i] == a[i + 1] && b[i] == b[i + 1] && a[i] != b[i]);
+            if let Some(i) = aligned {
+                return Ok(i * bs - n);
+            }
+        }
+        bail!("couldn't line up the input with a block")
+    }
+
+    /// Recovers the secret. We pad the prefix out to a block boundary, then for each byte of
+    /// the secret, feed just enough input that it's the last byte of a block, and compare that
+    /// block against every possible value.
+    pub fn recover(&self) -> anyhow::Result<Vec<u8>> {
+        let bs = self.block_size;
+        let align = (bs - self.prefix_len % bs) % bs;
+        let start = (self.prefix_len + align) / bs;
+
+        // The bytes we feed in, followed by the secret as we learn it. The block ending at any
+        // secret byte is made up of the bs - 1 bytes before it in here.
+        let mut known = vec![b'A'; bs - 1];
+        for i in 0..self.secret_len {
+            let fill = vec![b'A'; align + bs - 1 - i % bs];
+            let target = (start + i / bs) * bs;
+            let ciphertext = (self.oracle)(&fill);
+            let target = &ciphertext[target..target + bs];
+
+            let window = &known[known.len() - (bs - 1)..];
+            let byte = (0..=255u8)
+                .find(|&c| {
+                    let input = [&vec![b'A'; align], window, &[c]].concat();
+                    let start = start * bs;
+                    (self.oracle)(&input)[start..start + bs] == *target
+                })
+                .ok_or_else(|| anyhow!("no match for secret byte {}", i))?;
+            known.push(byte);
+        }
+
+        Ok(known.split_off(bs - 1))
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn ecb_oracle(prefix: Vec<u8>, secret: Vec<u8>) -> impl Fn(&[u8]) -> Vec<u8> {
+        use crate::aes::aes128_ecb_encrypt;
+        use crate::util::pkcs7_pad;
+        use rand::RngCore;
+
+        let mut key = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut key);
+        move |input| aes128_ecb_encrypt(&pkcs7_pad(&[&prefix, input, &secret].concat(), 16), &key)
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        use rand::RngCore;
+
+        let mut rng = rand::thread_rng();
+        for (prefix_len, secret_len) in [(0, 0), (0, 1), (3, 15), (16, 16), (21, 40), (37, 17)] {
+            let mut prefix = vec![0u8; prefix_len];
+            let mut secret = vec![0u8; secret_len];
+            rng.fill_bytes(&mut prefix);
+            rng.fill_bytes(&mut secret);
+            // Include every byte value, and bytes that look like padding.
+            secret.extend([0xff, 0x00, 0x01]);
+
+            let attack = EcbByteAtATime::new(ecb_oracle(prefix, secret.clone())).unwrap();
+            assert_eq!(attack.block_size, 16);
+            assert_eq!(attack.prefix_len, prefix_len);
+            assert_eq!(attack.secret_len, secret.len());
+            assert_eq!(attack.recover().unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn test_prefix_lengths() {
+        use rand::RngCore;
+
+        let mut rng = rand::thread_rng();
+        let secret = b"Some secret that spans a couple of blocks".to_vec();
+        for prefix_len in 0..=50 {
+            let mut prefix = vec![0u8; prefix_len];
+            rng.fill_bytes(&mut prefix);
+            let attack = EcbByteAtATime::new(ecb_oracle(prefix, secret.clone())).unwrap();
+            assert_eq!(attack.prefix_len, prefix_len);
+            assert_eq!(attack.recover().unwrap(), secret);
+
+            // Prefixes made of repeated blocks, or of bytes that could pass for the filler or a
+            // sentinel, shouldn't confuse the alignment.
+            for byte in [0, b'F', b'S', b'T'] {
+                let prefix = vec![byte; prefix_len];
+                let secret = [vec![byte; 20], secret.clone()].concat();
+                let attack = EcbByteAtATime::new(ecb_oracle(prefix, secret)).unwrap();
+                assert_eq!(attack.prefix_len, prefix_len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_ecb() {
+        use crate::util::pkcs7_pad;
+        use rand::RngCore;
+
+        let oracle = |input: &[u8]| {
+            let mut output = pkcs7_pad(input, 16);
+            rand::thread_rng().fill_bytes(&mut output);
+            output
+        };
+        assert!(EcbByteAtATime::new(oracle).is_err());
+    }
+}
@@ -1,9 +1,29 @@
 use std::env;
 
 mod aes;
+#[cfg(feature = "bignum")]
+mod bignum;
+#[cfg(feature = "bignum")]
+mod dh;
+#[cfg(feature = "bignum")]
+mod dsa;
+mod ecb;
+mod md4;
+mod mdhash;
+#[cfg(feature = "bignum")]
+mod net;
+mod rc4;
+#[cfg(feature = "bignum")]
+mod rsa;
 mod set1;
 mod set2;
+#[cfg(feature = "bignum")]
+mod set5;
+#[cfg(feature = "bignum")]
+mod set6;
 mod set7;
+#[cfg(feature = "bignum")]
+mod srp;
 mod util;
 
 fn init_logger() {
@@ -16,17 +36,25 @@ fn init_logger() {
 fn main() {
     init_logger();
     let mut skip_slow_challenges = false;
+    let mut verify_md4_conditions = false;
 
     env::args().skip(1).for_each(|arg| {
         tracing::info!("Running {}", arg);
         if arg == "--fast" {
             skip_slow_challenges = true;
+        } else if arg == "--verify-md4" {
+            verify_md4_conditions = true;
         }
     });
 
     set1::run();
-    set2::run(skip_slow_challenges);
-    set7::run();
+    set2::run();
+    #[cfg(feature = "bignum")]
+    {
+        set5::run();
+        set6::run(skip_slow_challenges);
+    }
+    set7::run(skip_slow_challenges, verify_md4_conditions);
 
     println!("All done!")
 }
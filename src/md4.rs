@@ -0,0 +1,168 @@
+pub const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+pub const ROUND2_CONSTANT: u32 = 0x5a827999;
+pub const ROUND3_CONSTANT: u32 = 0x6ed9eba1;
+
+/// Rotation amounts for the four steps in each group of four, per round.
+pub const ROUND1_SHIFTS: [u32; 4] = [3, 7, 11, 19];
+pub const ROUND2_SHIFTS: [u32; 4] = [3, 5, 9, 13];
+pub const ROUND3_SHIFTS: [u32; 4] = [3, 9, 11, 15];
+
+/// The order message words are used in, per round.
+pub const ROUND2_ORDER: [usize; 16] = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+pub const ROUND3_ORDER: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+/// a <- (a + F(b, c, d) + x) <<< s
+pub fn round1_step(a: u32, b: u32, c: u32, d: u32, x: u32, s: u32) -> u32 {
+    a.wrapping_add(f(b, c, d)).wrapping_add(x).rotate_left(s)
+}
+
+/// a <- (a + G(b, c, d) + x + 0x5a827999) <<< s
+pub fn round2_step(a: u32, b: u32, c: u32, d: u32, x: u32, s: u32) -> u32 {
+    a.wrapping_add(g(b, c, d))
+        .wrapping_add(x)
+        .wrapping_add(ROUND2_CONSTANT)
+        .rotate_left(s)
+}
+
+/// a <- (a + H(b, c, d) + x + 0x6ed9eba1) <<< s
+pub fn round3_step(a: u32, b: u32, c: u32, d: u32, x: u32, s: u32) -> u32 {
+    a.wrapping_add(h(b, c, d))
+        .wrapping_add(x)
+        .wrapping_add(ROUND3_CONSTANT)
+        .rotate_left(s)
+}
+
+/// Solves `round1_step` for the message word: the `x` that takes `a` to `result`.
+pub fn round1_word(a: u32, b: u32, c: u32, d: u32, result: u32, s: u32) -> u32 {
+    result
+        .rotate_right(s)
+        .wrapping_sub(a)
+        .wrapping_sub(f(b, c, d))
+}
+
+/// Solves `round2_step` for the message word.
+pub fn round2_word(a: u32, b: u32, c: u32, d: u32, result: u32, s: u32) -> u32 {
+    round1_word(a, b, c, d, result, s)
+        .wrapping_add(f(b, c, d))
+        .wrapping_sub(g(b, c, d))
+        .wrapping_sub(ROUND2_CONSTANT)
+}
+
+/// Splits a 64-byte block into little endian words.
+pub fn block_to_words(block: &[u8]) -> [u32; 16] {
+    assert_eq!(block.len(), 64);
+    let mut words = [0u32; 16];
+    for (word, chunk) in words.iter_mut().zip(block.chunks(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    words
+}
+
+pub fn words_to_block(words: &[u32; 16]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+/// The MD4 compression function. The round functions and constants it's built from are public,
+/// so attacks can step through it one operation at a time.
+pub fn compress(state: [u32; 4], x: &[u32; 16]) -> [u32; 4] {
+    let [mut a, mut b, mut c, mut d] = state;
+
+    for i in (0..16).step_by(4) {
+        a = round1_step(a, b, c, d, x[i], ROUND1_SHIFTS[0]);
+        d = round1_step(d, a, b, c, x[i + 1], ROUND1_SHIFTS[1]);
+        c = round1_step(c, d, a, b, x[i + 2], ROUND1_SHIFTS[2]);
+        b = round1_step(b, c, d, a, x[i + 3], ROUND1_SHIFTS[3]);
+    }
+
+    for i in (0..16).step_by(4) {
+        let k = &ROUND2_ORDER[i..i + 4];
+        a = round2_step(a, b, c, d, x[k[0]], ROUND2_SHIFTS[0]);
+        d = round2_step(d, a, b, c, x[k[1]], ROUND2_SHIFTS[1]);
+        c = round2_step(c, d, a, b, x[k[2]], ROUND2_SHIFTS[2]);
+        b = round2_step(b, c, d, a, x[k[3]], ROUND2_SHIFTS[3]);
+    }
+
+    for i in (0..16).step_by(4) {
+        let k = &ROUND3_ORDER[i..i + 4];
+        a = round3_step(a, b, c, d, x[k[0]], ROUND3_SHIFTS[0]);
+        d = round3_step(d, a, b, c, x[k[1]], ROUND3_SHIFTS[1]);
+        c = round3_step(c, d, a, b, x[k[2]], ROUND3_SHIFTS[2]);
+        b = round3_step(b, c, d, a, x[k[3]], ROUND3_SHIFTS[3]);
+    }
+
+    [
+        state[0].wrapping_add(a),
+        state[1].wrapping_add(b),
+        state[2].wrapping_add(c),
+        state[3].wrapping_add(d),
+    ]
+}
+
+/// Pads a message of `len` bytes with a 1 bit, zeros, and the length in bits as a 64-bit little
+/// endian integer, returning just the padding.
+pub fn md4_padding(len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80];
+    while (len + padding.len()) % 64 != 56 {
+        padding.push(0);
+    }
+    padding.extend((len as u64 * 8).to_le_bytes());
+    padding
+}
+
+/// MD4, as specified in RFC 1320.
+pub fn md4(message: &[u8]) -> Vec<u8> {
+    let padded = [message, &md4_padding(message.len())].concat();
+    let state = padded.chunks(64).fold(INITIAL_STATE, |state, block| {
+        compress(state, &block_to_words(block))
+    });
+    state.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_md4() {
+        // Test vectors from RFC 1320.
+        let vectors = [
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+        for (message, digest) in vectors {
+            assert_eq!(hex::encode(md4(message.as_bytes())), digest);
+        }
+    }
+
+    #[test]
+    fn test_round_words() {
+        let (a, b, c, d, x) = (0x01234567, 0x89abcdef, 0xfedcba98, 0x76543210, 0xdeadbeef);
+        let result = round1_step(a, b, c, d, x, 7);
+        assert_eq!(round1_word(a, b, c, d, result, 7), x);
+        let result = round2_step(a, b, c, d, x, 9);
+        assert_eq!(round2_word(a, b, c, d, result, 9), x);
+    }
+}
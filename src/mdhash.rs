@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::bail;
+use rand::RngCore;
+use rayon::prelude::*;
+
+use crate::aes::aes128_encrypt_block;
+
+pub const BLOCK_SIZE: usize = 16;
+
+/// A toy Merkle-Damgård hash with a deliberately tiny state. The compression function pads the
+/// state out to an AES key, encrypts the message block under it, and truncates the result back
+/// to the size of the state. Counts compression function calls, so attacks can report their
+/// cost.
+pub struct MdHash {
+    iv: Vec<u8>,
+    calls: AtomicU64,
+}
+
+/// Two different blocks that take `state` to the same next state.
+#[derive(Clone, Debug)]
+pub struct Collision {
+    pub blocks: [Vec<u8>; 2],
+    pub state: Vec<u8>,
+}
+
+impl MdHash {
+    /// Returns a hash with the given initial state. The output is the same size as the state.
+    pub fn new(iv: &[u8]) -> Self {
+        assert!(!iv.is_empty() && iv.len() <= 16);
+        Self {
+            iv: iv.to_vec(),
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    /// The number of compression function calls made so far.
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub fn compress(&self, state: &[u8], block: &[u8]) -> Vec<u8> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let mut key = state.to_vec();
+        key.resize(16, 0);
+        aes128_encrypt_block(block, &key)[..self.iv.len()].to_vec()
+    }
+
+    /// Runs the compression function over `message`, which must be a whole number of blocks,
+    /// starting from `state`.
+    pub fn iterate(&self, state: &[u8], message: &[u8]) -> Vec<u8> {
+        assert_eq!(message.len() % BLOCK_SIZE, 0);
+        message
+            .chunks(BLOCK_SIZE)
+            .fold(state.to_vec(), |state, block| self.compress(&state, block))
+    }
+
+    pub fn hash(&self, message: &[u8]) -> Vec<u8> {
+        self.iterate(&self.iv, &md_pad(message))
+    }
+}
+
+/// Pads `message` to a whole number of blocks with a 1 bit, zeros, and the length of the message
+/// in bits as a 64-bit big endian integer.
+pub fn md_pad(message: &[u8]) -> Vec<u8> {
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        padded.push(0);
+    }
+    padded.extend((message.len() as u64 * 8).to_be_bytes());
+    padded
+}
+
+fn random_block() -> Vec<u8> {
+    let mut block = vec![0u8; BLOCK_SIZE];
+    rand::thread_rng().fill_bytes(&mut block);
+    block
+}
+
+/// Finds a pair of single-block messages that collide from `state`, using the birthday paradox:
+/// for an n-bit state, this takes about 2^(n/2) compression function calls.
+pub fn find_collision(hash: &MdHash, state: &[u8]) -> Collision {
+    let mut seen: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    loop {
+        let block = random_block();
+        let next = hash.compress(state, &block);
+        match seen.get(&next) {
+            Some(other) if other != &block => {
+                return Collision {
+                    blocks: [other.clone(), block],
+                    state: next,
+                };
+            }
+            _ => {
+                seen.insert(next, block);
+            }
+        }
+    }
+}
+
+/// Finds `n` collisions in a row, starting from `state`. Picking either block from each one
+/// gives 2^n messages of n blocks that all collide, for only n times the cost of one collision
+/// (Joux).
+pub fn multicollision(hash: &MdHash, state: &[u8], n: usize) -> Vec<Collision> {
+    let mut collisions: Vec<Collision> = Vec::with_capacity(n);
+    for _ in 0..n {
+        let state = collisions.last().map_or(state, |c| &c.state);
+        collisions.push(find_collision(hash, state));
+    }
+    collisions
+}
+
+/// Returns all 2^n messages made up of one block from each of the `n` collisions.
+pub fn multicollision_messages(collisions: &[Collision]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    (0..1u64 << collisions.len()).map(move |choice| {
+        collisions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| c.blocks[(choice >> i & 1) as usize].clone())
+            .collect()
+    })
+}
+
+/// Finds a block from `a` and a block from `b` that lead to the same state. This costs about
+/// twice as much as a collision from a single state, since only pairs across the two sides count.
+pub fn find_collision_between(hash: &MdHash, a: &[u8], b: &[u8]) -> Collision {
+    let mut seen_a: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    let mut seen_b: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+    loop {
+        let block = random_block();
+        let next = hash.compress(a, &block);
+        if let Some(other) = seen_b.get(&next) {
+            return Collision {
+                blocks: [block, other.clone()],
+                state: next,
+            };
+        }
+        seen_a.insert(next, block);
+
+        let block = random_block();
+        let next = hash.compress(b, &block);
+        if let Some(other) = seen_a.get(&next) {
+            return Collision {
+                blocks: [other.clone(), block],
+                state: next,
+            };
+        }
+        seen_b.insert(next, block);
+    }
+}
+
+/// A set of messages of every length from k to k + 2^k - 1 blocks, which all lead to the same
+/// state (Kelsey and Schneier). Piece i is a collision between a single block and 2^i dummy
+/// blocks followed by one more block, so we can pick the length one bit at a time.
+pub struct ExpandableMessage {
+    pieces: Vec<[Vec<u8>; 2]>,
+    pub state: Vec<u8>,
+}
+
+impl ExpandableMessage {
+    pub fn new(hash: &MdHash, state: &[u8], k: usize) -> Self {
+        let mut pieces = Vec::with_capacity(k);
+        let mut state = state.to_vec();
+        for i in (0..k).rev() {
+            let dummy = vec![0u8; BLOCK_SIZE << i];
+            let collision = find_collision_between(hash, &state, &hash.iterate(&state, &dummy));
+            let [short, last] = collision.blocks;
+            pieces.push([short, [dummy, last].concat()]);
+            state = collision.state;
+        }
+
+        Self { pieces, state }
+    }
+
+    /// The range of lengths, in blocks, this can produce.
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1 << self.pieces.len()) - 1
+    }
+
+    /// Returns the message of exactly `blocks` blocks.
+    pub fn produce(&self, blocks: usize) -> Vec<u8> {
+        assert!((self.min_blocks()..=self.max_blocks()).contains(&blocks));
+        let extra = blocks - self.min_blocks();
+        let k = self.pieces.len();
+        self.pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(j, piece)| piece[extra >> (k - 1 - j) & 1].clone())
+            .collect()
+    }
+}
+
+/// Finds a second preimage for `message`, a whole number of blocks, using an expandable message
+/// with parameter k. We look for a bridge block from the end of the expandable message to any of
+/// the intermediate states of `message`, then fill in the blocks before it with the expandable
+/// message, so the forgery has the same length and the same padding. The longer the message, the
+/// more states there are to hit: for an n-bit state and 2^k blocks, this takes about
+/// k * 2^(n/2 + 1) + 2^(n - k) compression function calls, rather than 2^n.
+pub fn second_preimage(hash: &MdHash, message: &[u8], k: usize) -> anyhow::Result<Vec<u8>> {
+    if !message.len().is_multiple_of(BLOCK_SIZE) {
+        bail!("message must be a whole number of blocks");
+    }
+    let blocks = message.len() / BLOCK_SIZE;
+    if blocks <= k {
+        bail!("message must be longer than {} blocks", k);
+    }
+
+    let expandable = ExpandableMessage::new(hash, hash.iv(), k);
+
+    // The bridge replaces block i - 1, so the expandable message needs to be i - 1 blocks long.
+    let mut targets = HashMap::new();
+    let mut state = hash.iv().to_vec();
+    for (i, block) in message
+        .chunks(BLOCK_SIZE)
+        .enumerate()
+        .map(|(i, b)| (i + 1, b))
+    {
+        state = hash.compress(&state, block);
+        if (expandable.min_blocks()..=expandable.max_blocks()).contains(&(i - 1)) {
+            targets.insert(state.clone(), i);
+        }
+    }
+
+    let (bridge, i) = iter::repeat_with(random_block)
+        .find_map(|block| {
+            let i = *targets.get(&hash.compress(&expandable.state, &block))?;
+            Some((block, i))
+        })
+        .unwrap();
+
+    Ok([
+        expandable.produce(i - 1),
+        bridge,
+        message[i * BLOCK_SIZE..].to_vec(),
+    ]
+    .concat())
+}
+
+/// A binary tree of collisions (Kelsey and Kohno). Each of the 2^k leaves is a state, and each
+/// pair of sibling nodes has a block apiece that leads both of them to the same parent state, so
+/// there's a k-block path from every leaf to the root.
+pub struct Diamond {
+    leaves: Vec<Vec<u8>>,
+    // blocks[level][i] takes node i at that level to its parent, node i / 2 at the next one.
+    blocks: Vec<Vec<Vec<u8>>>,
+    pub root: Vec<u8>,
+}
+
+impl Diamond {
+    /// Builds a diamond structure with 2^k random leaves. The collisions at each level are
+    /// independent, so they're found in parallel.
+    pub fn new(hash: &MdHash, k: usize) -> Self {
+        let state_len = hash.iv().len();
+        let leaves = (0..1 << k)
+            .map(|_| random_block()[..state_len].to_vec())
+            .collect::<Vec<_>>();
+
+        let mut blocks = Vec::with_capacity(k);
+        let mut states = leaves.clone();
+        while states.len() > 1 {
+            let collisions = states
+                .par_chunks(2)
+                .map(|pair| find_collision_between(hash, &pair[0], &pair[1]))
+                .collect::<Vec<_>>();
+            states = collisions.iter().map(|c| c.state.clone()).collect();
+            blocks.push(collisions.into_iter().flat_map(|c| c.blocks).collect());
+        }
+
+        Self {
+            leaves,
+            blocks,
+            root: states.remove(0),
+        }
+    }
+
+    pub fn k(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// The digest to publish in advance, for a message with a `prefix_blocks`-block prefix
+    /// (counting the zeros `herd` pads it with), followed by a linking block and the path through
+    /// the diamond. The length padding is the only thing after the root, so it's already fixed.
+    pub fn commitment(&self, hash: &MdHash, prefix_blocks: usize) -> Vec<u8> {
+        let len = (prefix_blocks + 1 + self.k()) * BLOCK_SIZE;
+        hash.iterate(&self.root, &md_pad(&vec![0; len])[len..])
+    }
+
+    /// Returns `prefix`, padded with zeros to a whole number of blocks, followed by a block
+    /// linking it to one of the leaves, and the path from there to the root. The padded prefix
+    /// must be the `prefix_blocks` blocks the commitment was made for. For an n-bit state,
+    /// finding the link takes about 2^(n - k) calls.
+    pub fn herd(
+        &self,
+        hash: &MdHash,
+        prefix_blocks: usize,
+        prefix: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        if prefix.len().div_ceil(BLOCK_SIZE) != prefix_blocks {
+            bail!(
+                "prefix is {} bytes, but the commitment is for {} blocks",
+                prefix.len(),
+                prefix_blocks
+            );
+        }
+
+        let mut prefix = prefix.to_vec();
+        prefix.resize(prefix.len().next_multiple_of(BLOCK_SIZE), 0);
+        let leaves = self
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(i, leaf)| (leaf.clone(), i))
+            .collect::<HashMap<_, _>>();
+
+        let state = hash.iterate(hash.iv(), &prefix);
+        let (link, leaf) = iter::repeat_with(random_block)
+            .find_map(|block| {
+                let leaf = *leaves.get(&hash.compress(&state, &block))?;
+                Some((block, leaf))
+            })
+            .unwrap();
+
+        let path = self
+            .blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(level, blocks)| blocks[leaf >> level].clone());
+        Ok([prefix, link].concat().into_iter().chain(path).collect())
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_md_pad() {
+        for len in 0..40 {
+            let padded = md_pad(&vec![0xaa; len]);
+            assert_eq!(padded.len() % BLOCK_SIZE, 0);
+            assert_eq!(padded[len], 0x80);
+            assert_eq!(padded[padded.len() - 8..], (len as u64 * 8).to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_find_collision() {
+        let hash = MdHash::new(&[1, 2]);
+        let collision = find_collision(&hash, hash.iv());
+        let [a, b] = &collision.blocks;
+        assert_ne!(a, b);
+        assert_eq!(hash.compress(hash.iv(), a), collision.state);
+        assert_eq!(hash.compress(hash.iv(), b), collision.state);
+    }
+
+    #[test]
+    fn test_multicollision() {
+        let hash = MdHash::new(&[1, 2]);
+        let collisions = multicollision(&hash, hash.iv(), 4);
+        let mut messages = multicollision_messages(&collisions).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 16);
+
+        let digest = hash.hash(&messages[0]);
+        assert!(messages.iter().all(|m| hash.hash(m) == digest));
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), 16);
+    }
+
+    #[test]
+    fn test_expandable_message() {
+        let hash = MdHash::new(&[1, 2]);
+        let expandable = ExpandableMessage::new(&hash, hash.iv(), 4);
+        assert_eq!((expandable.min_blocks(), expandable.max_blocks()), (4, 19));
+        for blocks in 4..=19 {
+            let message = expandable.produce(blocks);
+            assert_eq!(message.len(), blocks * BLOCK_SIZE);
+            assert_eq!(hash.iterate(hash.iv(), &message), expandable.state);
+        }
+    }
+
+    #[test]
+    fn test_second_preimage() {
+        let hash = MdHash::new(&[1, 2]);
+        let mut message = vec![0u8; 16 * BLOCK_SIZE];
+        rand::thread_rng().fill_bytes(&mut message);
+
+        let forged = second_preimage(&hash, &message, 4).unwrap();
+        assert_ne!(forged, message);
+        assert_eq!(hash.hash(&forged), hash.hash(&message));
+
+        assert!(second_preimage(&hash, &message[..4 * BLOCK_SIZE], 4).is_err());
+        assert!(second_preimage(&hash, &message[..100], 4).is_err());
+    }
+
+    #[test]
+    fn test_herding() {
+        let hash = MdHash::new(&[1, 2]);
+        let diamond = Diamond::new(&hash, 4);
+        let commitment = diamond.commitment(&hash, 2);
+
+        let prefix = [b'x'; 2 * BLOCK_SIZE];
+        let message = diamond.herd(&hash, 2, &prefix).unwrap();
+        assert!(message.starts_with(&prefix));
+        assert_eq!(message.len(), (2 + 1 + 4) * BLOCK_SIZE);
+        assert_eq!(hash.hash(&message), commitment);
+
+        // Prefixes that end partway through a block are padded out to the next one.
+        let prefix = [b'y'; BLOCK_SIZE + 5];
+        let message = diamond.herd(&hash, 2, &prefix).unwrap();
+        assert!(message.starts_with(&prefix));
+        assert_eq!(message.len(), (2 + 1 + 4) * BLOCK_SIZE);
+        assert_eq!(hash.hash(&message), commitment);
+
+        // A prefix of any other length wouldn't hash to the commitment.
+        assert!(diamond.herd(&hash, 2, &[b'z'; BLOCK_SIZE]).is_err());
+        assert!(diamond.herd(&hash, 2, &[b'z'; 2 * BLOCK_SIZE + 1]).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::anyhow;
+
+/// The direction a message is travelling in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    AtoB,
+    BtoA,
+}
+
+/// An actor sitting between A and B. Every message passes through `intercept`, which can
+/// observe it, rewrite it, or drop it entirely by returning `None`.
+pub trait Mitm<M>: Send {
+    fn intercept(&mut self, direction: Direction, message: M) -> Option<M>;
+}
+
+/// A MITM that forwards every message untouched, i.e., a plain wire.
+pub struct Passthrough;
+
+impl<M> Mitm<M> for Passthrough {
+    fn intercept(&mut self, _: Direction, message: M) -> Option<M> {
+        Some(message)
+    }
+}
+
+/// One end of a link between two parties.
+pub struct Endpoint<M> {
+    tx: Sender<M>,
+    rx: Receiver<M>,
+}
+
+impl<M> Endpoint<M> {
+    pub fn send(&self, message: M) -> anyhow::Result<()> {
+        self.tx.send(message).map_err(|_| anyhow!("peer hung up"))
+    }
+
+    pub fn recv(&self) -> anyhow::Result<M> {
+        self.rx.recv().map_err(|_| anyhow!("peer hung up"))
+    }
+}
+
+/// Connects two parties through `mitm`, returning the endpoints for A and B. Each direction is
+/// relayed by its own thread, which exits once the sending endpoint is dropped. Keep a clone of
+/// `mitm` to inspect whatever it captured after the exchange.
+pub fn link<M: Send + 'static>(mitm: Arc<Mutex<dyn Mitm<M>>>) -> (Endpoint<M>, Endpoint<M>) {
+    let (a_tx, a_to_relay) = channel();
+    let (b_tx, b_to_relay) = channel();
+    let (relay_to_a, a_rx) = channel();
+    let (relay_to_b, b_rx) = channel();
+
+    relay(Direction::AtoB, a_to_relay, relay_to_b, mitm.clone());
+    relay(Direction::BtoA, b_to_relay, relay_to_a, mitm);
+
+    (
+        Endpoint { tx: a_tx, rx: a_rx },
+        Endpoint { tx: b_tx, rx: b_rx },
+    )
+}
+
+/// Connects two parties directly, with nobody in the middle.
+pub fn direct_link<M: Send + 'static>() -> (Endpoint<M>, Endpoint<M>) {
+    link(Arc::new(Mutex::new(Passthrough)))
+}
+
+fn relay<M: Send + 'static>(
+    direction: Direction,
+    rx: Receiver<M>,
+    tx: Sender<M>,
+    mitm: Arc<Mutex<dyn Mitm<M>>>,
+) {
+    thread::spawn(move || {
+        for message in rx {
+            let message = mitm.lock().unwrap().intercept(direction, message);
+            if let Some(message) = message {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_direct_link() {
+        let (a, b) = direct_link::<u32>();
+        a.send(1).unwrap();
+        assert_eq!(b.recv().unwrap(), 1);
+        b.send(2).unwrap();
+        assert_eq!(a.recv().unwrap(), 2);
+
+        drop(a);
+        assert!(b.recv().is_err());
+    }
+
+    #[test]
+    fn test_mitm() {
+        // Doubles everything going from A to B, drops everything going the other way.
+        struct Doubler(Vec<u32>);
+        impl Mitm<u32> for Doubler {
+            fn intercept(&mut self, direction: Direction, message: u32) -> Option<u32> {
+                self.0.push(message);
+                match direction {
+                    Direction::AtoB => Some(message * 2),
+                    Direction::BtoA => None,
+                }
+            }
+        }
+
+        let mitm = Arc::new(Mutex::new(Doubler(vec![])));
+        let (a, b) = link(mitm.clone());
+        a.send(21).unwrap();
+        assert_eq!(b.recv().unwrap(), 42);
+        b.send(7).unwrap();
+        a.send(1).unwrap();
+        assert_eq!(b.recv().unwrap(), 2);
+        drop(b);
+        assert!(a.recv().is_err());
+
+        assert_eq!(mitm.lock().unwrap().0.len(), 3);
+    }
+}
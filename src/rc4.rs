@@ -0,0 +1,157 @@
+use rayon::prelude::*;
+use tracing::*;
+
+/// The RC4 stream cipher.
+pub struct Rc4 {
+    s: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    /// Runs the key schedule.
+    pub fn new(key: &[u8]) -> Self {
+        assert!(!key.is_empty() && key.len() <= 256);
+        let mut s = [0u8; 256];
+        for (i, x) in s.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+
+        let mut j = 0u8;
+        for (i, &k) in (0..256).zip(key.iter().cycle()) {
+            j = j.wrapping_add(s[i]).wrapping_add(k);
+            s.swap(i, j as usize);
+        }
+
+        Self { s, i: 0, j: 0 }
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.s[self.i as usize]);
+        self.s.swap(self.i as usize, self.j as usize);
+        self.s[self.s[self.i as usize].wrapping_add(self.s[self.j as usize]) as usize]
+    }
+
+    /// Encrypts or decrypts `data` in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for b in data.iter_mut() {
+            *b ^= self.next_byte();
+        }
+    }
+}
+
+/// Encrypts or decrypts `data` under `key`.
+pub fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut output = data.to_vec();
+    Rc4::new(key).apply(&mut output);
+    output
+}
+
+/// Recovers a secret of up to 32 bytes appended to our request, from the biases in RC4's
+/// keystream: byte 16 is 0xf0 and byte 32 is 0xe0 noticeably more often than any other value.
+/// For each prefix length, we encrypt `samples` times and take the most common ciphertext bytes
+/// at those positions. Prefix lengths 0 through 15 line every secret byte up with one of them;
+/// we skip the ones that don't line up any.
+pub fn rc4_bias_attack(oracle: impl Fn(&[u8]) -> Vec<u8> + Sync, samples: u64) -> Vec<u8> {
+    let secret_len = oracle(&[]).len();
+    assert!(secret_len <= 32);
+
+    let mut secret = vec![0u8; secret_len];
+    for prefix_len in 0..16 {
+        let covered = |position| (prefix_len..prefix_len + secret_len).contains(&position);
+        if !covered(15) && !covered(31) {
+            continue;
+        }
+
+        let request = vec![b'A'; prefix_len];
+        let counts = (0..samples)
+            .into_par_iter()
+            .fold(
+                || vec![[0u64; 256]; 2],
+                |mut counts, _| {
+                    let ciphertext = oracle(&request);
+                    for (counts, position) in counts.iter_mut().zip([15, 31]) {
+                        if let Some(&b) = ciphertext.get(position) {
+                            counts[b as usize] += 1;
+                        }
+                    }
+                    counts
+                },
+            )
+            .reduce(
+                || vec![[0u64; 256]; 2],
+                |mut a, b| {
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    }
+                    a
+                },
+            );
+
+        let most_common = |counts: &[u64; 256]| (0..=255u8).max_by_key(|&b| counts[b as usize]);
+        for (position, counts, bias) in [(15, &counts[0], 0xf0), (31, &counts[1], 0xe0)] {
+            if covered(position) {
+                secret[position - prefix_len] = most_common(counts).unwrap() ^ bias;
+            }
+        }
+        debug!("{:?}", String::from_utf8_lossy(&secret));
+    }
+
+    secret
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_rc4() {
+        let vectors = [
+            ("Key", "Plaintext", "bbf316e8d940af0ad3"),
+            ("Wiki", "pedia", "1021bf0420"),
+            ("Secret", "Attack at dawn", "45a01f645fc35b383552544b9bf5"),
+        ];
+        for (key, plaintext, ciphertext) in vectors {
+            let encrypted = rc4(key.as_bytes(), plaintext.as_bytes());
+            assert_eq!(hex::encode(&encrypted), ciphertext);
+            assert_eq!(rc4(key.as_bytes(), &encrypted), plaintext.as_bytes());
+        }
+    }
+
+    /// Like `rc4_bias_attack`'s real target, but with much stronger biases, so the attack works
+    /// from a few thousand samples: keystream bytes 16 and 32 are 0xf0 and 0xe0 a quarter of
+    /// the time.
+    #[allow(dead_code)]
+    fn biased_oracle(secret: &[u8]) -> impl Fn(&[u8]) -> Vec<u8> + Sync + '_ {
+        use rand::{Rng, RngCore};
+
+        move |request| {
+            let mut rng = rand::thread_rng();
+            let mut keystream = [0u8; 48];
+            rng.fill_bytes(&mut keystream);
+            for (position, bias) in [(15, 0xf0), (31, 0xe0)] {
+                if rng.gen_range(0..4) == 0 {
+                    keystream[position] = bias;
+                }
+            }
+            let plaintext = [request, secret].concat();
+            plaintext
+                .iter()
+                .zip(keystream)
+                .map(|(a, b)| a ^ b)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_rc4_bias_attack() {
+        for secret in [
+            &b"short"[..],
+            b"exactly sixteen!",
+            b"rather longer than sixteen bytes",
+        ] {
+            assert_eq!(rc4_bias_attack(biased_oracle(secret), 1 << 12), secret);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::bail;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::Zero;
+use rand::Rng;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::bignum::*;
+
+/// Hash functions supported for PKCS#1 v1.5 signatures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlg {
+    Sha1,
+    Sha256,
+}
+
+impl HashAlg {
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlg::Sha1 => Sha1::digest(message).to_vec(),
+            HashAlg::Sha256 => Sha256::digest(message).to_vec(),
+        }
+    }
+
+    /// The DER-encoded DigestInfo header (algorithm identifier and octet string tag) that
+    /// precedes the digest in a PKCS#1 v1.5 signature.
+    pub fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            HashAlg::Sha1 => &[
+                0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+                0x14,
+            ],
+            HashAlg::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+        }
+    }
+}
+
+/// Encodes the hash of `message` as a `k`-byte PKCS#1 v1.5 signature block:
+///
+///   00 01 FF FF ... FF 00 DigestInfo Hash(message)
+pub fn pkcs1v15_sig_encode(hash: HashAlg, message: &[u8], k: usize) -> Vec<u8> {
+    let t = [hash.digest_info_prefix(), &hash.digest(message)].concat();
+    assert!(k >= t.len() + 11, "key too small for digest");
+    [&[0x00, 0x01], &vec![0xff; k - t.len() - 3][..], &[0x00], &t].concat()
+}
+
+/// Pads `message` into a `k`-byte PKCS#1 v1.5 encryption block:
+///
+///   00 02 PS 00 message
+///
+/// where PS is at least 8 random non-zero bytes.
+pub fn pkcs1v15_pad(message: &[u8], k: usize) -> Vec<u8> {
+    assert!(message.len() + 11 <= k, "message too long for key");
+    let mut rng = rand::thread_rng();
+    let ps = (0..k - message.len() - 3)
+        .map(|_| rng.gen_range(1..=255u8))
+        .collect::<Vec<_>>();
+    [&[0x00, 0x02], &ps[..], &[0x00], message].concat()
+}
+
+/// Removes PKCS#1 v1.5 encryption padding, checking the header, the length of the padding
+/// string, and the separator.
+pub fn pkcs1v15_unpad(block: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let Some(rest) = block.strip_prefix(&[0x00, 0x02]) else {
+        bail!("Invalid padding: bad header");
+    };
+
+    let Some(separator) = rest.iter().position(|&b| b == 0x00) else {
+        bail!("Invalid padding: no separator");
+    };
+    if separator < 8 {
+        bail!("Invalid padding: padding string too short");
+    }
+
+    Ok(rest[separator + 1..].to_vec())
+}
+
+/// An RSA public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    pub n: BigUint,
+    pub e: BigUint,
+}
+
+/// An RSA private key, along with the primes and CRT parameters used to speed up decryption.
+#[derive(Clone, Debug)]
+pub struct RsaPrivateKey {
+    pub n: BigUint,
+    pub e: BigUint,
+    pub d: BigUint,
+    pub p: BigUint,
+    pub q: BigUint,
+    /// d mod (p - 1)
+    pub dp: BigUint,
+    /// d mod (q - 1)
+    pub dq: BigUint,
+    /// q^-1 mod p
+    pub qinv: BigUint,
+}
+
+impl RsaPublicKey {
+    /// Returns the size of the modulus in bytes.
+    pub fn size(&self) -> usize {
+        self.n.bits().div_ceil(8) as usize
+    }
+
+    /// Returns `m^e mod n`.
+    pub fn encrypt_int(&self, m: &BigUint) -> BigUint {
+        assert!(m < &self.n, "message too large for modulus");
+        modexp(m, &self.e, &self.n)
+    }
+
+    /// Encrypts `message` (interpreted as a big-endian integer) with no padding, returning a
+    /// ciphertext the size of the modulus.
+    pub fn encrypt(&self, message: &[u8]) -> Vec<u8> {
+        to_bytes_padded(&self.encrypt_int(&from_bytes(message)), self.size())
+    }
+
+    /// Encrypts `message` with PKCS#1 v1.5 padding.
+    pub fn encrypt_pkcs1v15(&self, message: &[u8]) -> Vec<u8> {
+        self.encrypt(&pkcs1v15_pad(message, self.size()))
+    }
+
+    /// Returns `s^e * c mod n`, a ciphertext that decrypts to `s * m mod n`, where `m` is the
+    /// decryption of `c`.
+    pub fn blind(&self, c: &BigUint, s: &BigUint) -> BigUint {
+        modexp(s, &self.e, &self.n) * c % &self.n
+    }
+
+    /// Verifies a PKCS#1 v1.5 signature by encoding the expected signature block and comparing
+    /// it against the whole decrypted signature.
+    pub fn verify_pkcs1v15(&self, hash: HashAlg, message: &[u8], signature: &[u8]) -> bool {
+        let s = from_bytes(signature);
+        if signature.len() != self.size() || s >= self.n {
+            return false;
+        }
+
+        let block = to_bytes_padded(&self.encrypt_int(&s), self.size());
+        block == pkcs1v15_sig_encode(hash, message, self.size())
+    }
+
+    /// Verifies a PKCS#1 v1.5 signature the sloppy way, by parsing the signature block from
+    /// the left and never checking that the digest runs to the end of the block. Anything can
+    /// follow the digest, which is what makes Bleichenbacher's e=3 forgery possible. Don't use
+    /// this for anything but attacking it.
+    pub fn verify_pkcs1v15_sloppy(&self, hash: HashAlg, message: &[u8], signature: &[u8]) -> bool {
+        let s = from_bytes(signature);
+        if s >= self.n {
+            return false;
+        }
+
+        let block = to_bytes_padded(&self.encrypt_int(&s), self.size());
+        let Some(rest) = block.strip_prefix(&[0x00, 0x01]) else {
+            return false;
+        };
+
+        // Skip over at least one 0xff byte of padding, up to the 0x00 separator.
+        let padding = rest.iter().take_while(|&&b| b == 0xff).count();
+        if padding == 0 {
+            return false;
+        }
+        let Some(rest) = rest[padding..].strip_prefix(&[0x00]) else {
+            return false;
+        };
+
+        let Some(rest) = rest.strip_prefix(hash.digest_info_prefix()) else {
+            return false;
+        };
+        rest.starts_with(&hash.digest(message))
+    }
+
+    /// Queries `oracle` with `c` blinded by `s`. The oracle sees a ciphertext unrelated to `c`,
+    /// but its answer is about the plaintext `s * m mod n`, which is the basis of most attacks on
+    /// unpadded RSA oracles.
+    pub fn blinded_query<R>(
+        &self,
+        c: &BigUint,
+        s: &BigUint,
+        oracle: impl FnOnce(&BigUint) -> R,
+    ) -> R {
+        oracle(&self.blind(c, s))
+    }
+}
+
+impl RsaPrivateKey {
+    /// Generates a key with a `bits`-bit modulus and public exponent `e`.
+    pub fn generate(bits: u64, e: u32) -> Self {
+        let e = BigUint::from(e);
+        loop {
+            let p = gen_prime(bits / 2);
+            let q = gen_prime(bits - bits / 2);
+            let n = &p * &q;
+            if p == q || n.bits() != bits {
+                continue;
+            }
+
+            // e must be invertible mod (p - 1)(q - 1), which for small e like 3 rules out
+            // quite a few primes.
+            let et = (&p - 1u32) * (&q - 1u32);
+            if let Some(d) = modinv(&e, &et) {
+                return Self::from_primes(p, q, e, d);
+            }
+        }
+    }
+
+    fn from_primes(p: BigUint, q: BigUint, e: BigUint, d: BigUint) -> Self {
+        let dp = &d % (&p - 1u32);
+        let dq = &d % (&q - 1u32);
+        let qinv = modinv(&q, &p).unwrap();
+        Self {
+            n: &p * &q,
+            e,
+            d,
+            p,
+            q,
+            dp,
+            dq,
+            qinv,
+        }
+    }
+
+    pub fn public_key(&self) -> RsaPublicKey {
+        RsaPublicKey {
+            n: self.n.clone(),
+            e: self.e.clone(),
+        }
+    }
+
+    /// Returns `c^d mod n`, computed with the CRT exponents.
+    pub fn decrypt_int(&self, c: &BigUint) -> BigUint {
+        let m1 = modexp(c, &self.dp, &self.p);
+        let m2 = modexp(c, &self.dq, &self.q);
+
+        // m = m2 + q * (qinv * (m1 - m2) mod p)
+        let h = (&self.qinv * (&m1 + &self.p - &m2 % &self.p)).mod_floor(&self.p);
+        m2 + h * &self.q
+    }
+
+    /// Signs `message` with PKCS#1 v1.5 padding, returning a signature the size of the
+    /// modulus.
+    pub fn sign_pkcs1v15(&self, hash: HashAlg, message: &[u8]) -> Vec<u8> {
+        let k = self.public_key().size();
+        let block = from_bytes(&pkcs1v15_sig_encode(hash, message, k));
+        to_bytes_padded(&self.decrypt_int(&block), k)
+    }
+
+    /// Decrypts `ciphertext` and removes its PKCS#1 v1.5 padding.
+    pub fn decrypt_pkcs1v15(&self, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let k = self.public_key().size();
+        let block = self.decrypt_int(&from_bytes(ciphertext));
+        pkcs1v15_unpad(&to_bytes_padded(&block, k))
+    }
+
+    /// Decrypts `ciphertext` with no padding. Since the plaintext is just an integer, any
+    /// leading zero bytes in the original message are lost.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Vec<u8> {
+        let m = self.decrypt_int(&from_bytes(ciphertext));
+        if m.is_zero() {
+            vec![]
+        } else {
+            to_bytes(&m)
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_rsa_roundtrip() {
+        for e in [3, 65537] {
+            let key = RsaPrivateKey::generate(512, e);
+            let public = key.public_key();
+            assert_eq!(key.n.bits(), 512);
+            assert_eq!(public.size(), 64);
+            assert_eq!(
+                &key.e * &key.d % ((&key.p - 1u32) * (&key.q - 1u32)),
+                BigUint::from(1u32)
+            );
+
+            for _ in 0..8 {
+                let mut message = vec![0u8; rand::random::<usize>() % 63 + 1];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut message);
+                message[0] |= 1;
+
+                let ciphertext = public.encrypt(&message);
+                assert_eq!(ciphertext.len(), 64);
+                assert_eq!(key.decrypt(&ciphertext), message);
+
+                // The CRT shortcut should agree with the textbook c^d mod n.
+                let c = from_bytes(&ciphertext);
+                assert_eq!(key.decrypt_int(&c), modexp(&c, &key.d, &key.n));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pkcs1v15_signatures() {
+        let key = RsaPrivateKey::generate(1024, 3);
+        let public = key.public_key();
+
+        for hash in [HashAlg::Sha1, HashAlg::Sha256] {
+            let block = pkcs1v15_sig_encode(hash, b"hi mom", 128);
+            assert_eq!(block.len(), 128);
+            assert_eq!(&block[..3], &[0x00, 0x01, 0xff]);
+
+            let signature = key.sign_pkcs1v15(hash, b"hi mom");
+            assert!(public.verify_pkcs1v15(hash, b"hi mom", &signature));
+            assert!(public.verify_pkcs1v15_sloppy(hash, b"hi mom", &signature));
+            assert!(!public.verify_pkcs1v15(hash, b"hi dad", &signature));
+            assert!(!public.verify_pkcs1v15_sloppy(hash, b"hi dad", &signature));
+        }
+
+        // The wrong hash algorithm shouldn't verify either.
+        let signature = key.sign_pkcs1v15(HashAlg::Sha1, b"hi mom");
+        assert!(!public.verify_pkcs1v15(HashAlg::Sha256, b"hi mom", &signature));
+    }
+
+    #[test]
+    fn test_pkcs1v15_encryption() {
+        let key = RsaPrivateKey::generate(512, 65537);
+        let public = key.public_key();
+
+        for message in ["".as_bytes(), b"kick it, CC", &[0u8; 53]] {
+            let block = pkcs1v15_pad(message, 64);
+            assert_eq!(block.len(), 64);
+            assert_eq!(&block[..2], &[0x00, 0x02]);
+            assert!(block[2..10].iter().all(|&b| b != 0));
+            assert_eq!(pkcs1v15_unpad(&block).unwrap(), message);
+
+            let ciphertext = public.encrypt_pkcs1v15(message);
+            assert_eq!(key.decrypt_pkcs1v15(&ciphertext).unwrap(), message);
+        }
+
+        assert!(pkcs1v15_unpad(&[0x00, 0x01, 1, 1, 1, 1, 1, 1, 1, 1, 0x00]).is_err());
+        assert!(pkcs1v15_unpad(&[0x00, 0x02, 1, 1, 1, 1, 1, 1, 1, 0x00]).is_err());
+        assert!(pkcs1v15_unpad(&[0x00, 0x02, 1, 1, 1, 1, 1, 1, 1, 1, 1]).is_err());
+        assert!(pkcs1v15_unpad(&[0x00, 0x02, 1, 1, 1, 1, 1, 1, 1, 1, 0x00]).is_ok());
+    }
+
+    #[test]
+    fn test_blinding() {
+        let key = RsaPrivateKey::generate(512, 65537);
+        let public = key.public_key();
+        let m = BigUint::from(1234567u32);
+        let s = BigUint::from(42u32);
+
+        let c = public.encrypt_int(&m);
+        let blinded = public.blinded_query(&c, &s, |c| key.decrypt_int(c));
+        assert_eq!(blinded, &m * &s);
+    }
+}
@@ -85,7 +85,7 @@ fn score_englishness(text: Vec<u8>) -> f32 {
 
 fn solve_single_char_xor(input: Vec<u8>) -> (f32, u8, String) {
     let mut scores = [0.0; 256];
-    let mut min = std::f32::MAX;
+    let mut min = f32::MAX;
     let mut min_char = 0;
     for c in 0..255 {
         let mut result = Vec::new();
@@ -126,7 +126,7 @@ fn challenge4() {
     let data = std::fs::read_to_string("data/4.txt").unwrap();
     let lines = data.split('\n');
 
-    let mut min = std::f32::MAX;
+    let mut min = f32::MAX;
     let mut min_result = String::new();
     for line in lines {
         let (score, _, result) = solve_single_char_xor(hex::decode(line).unwrap());
@@ -192,7 +192,7 @@ fn challenge6() {
     // Sort the keysizes by distance (smallest to largest)
     keysize_distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
 
-    let mut min_score = std::f32::MAX;
+    let mut min_score = f32::MAX;
     let mut min_key = String::new();
     let mut min_result = vec![];
 
@@ -256,7 +256,7 @@ fn challenge8() {
         .collect::<Vec<_>>();
 
     // Find the line with the lowest average hamming distance between chunks.
-    let mut min_score = std::f32::MAX;
+    let mut min_score = f32::MAX;
     let mut best_line = 0;
     for (i, line) in data.iter().enumerate() {
         let mut score = 0.0;
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::aes::*;
+use crate::ecb::EcbByteAtATime;
 use crate::util::*;
 use base64::{engine::general_purpose, Engine};
 use rand::Rng;
@@ -112,19 +113,22 @@ fn challenge11() {
     }
 }
 
-fn oracle12(input: &[u8], key: &[u8]) -> Vec<u8> {
-    let suffix = {
-        let suffix = r"
+/// The secret the challenge 12 and 14 oracles append to their input.
+fn secret12() -> Vec<u8> {
+    let secret = r"
     Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkg
     aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq
     dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg
     YnkK
     "
-        .split_whitespace()
-        .collect::<Vec<&str>>()
-        .join("");
-        general_purpose::STANDARD.decode(suffix).unwrap()
-    };
+    .split_whitespace()
+    .collect::<Vec<&str>>()
+    .join("");
+    general_purpose::STANDARD.decode(secret).unwrap()
+}
+
+fn oracle12(input: &[u8], key: &[u8]) -> Vec<u8> {
+    let suffix = secret12();
 
     aes128_ecb_encrypt(
         pkcs7_pad([input, suffix.as_slice()].concat().as_slice(), 16).as_slice(),
@@ -132,90 +136,19 @@ fn oracle12(input: &[u8], key: &[u8]) -> Vec<u8> {
     )
 }
 
-type OracleFn = Box<dyn Fn(&[u8], &[u8]) -> Vec<u8>>;
-
-fn challenge12and14(oracle: OracleFn, detect_block_size: bool) {
-    info!("Running: challenge12and14");
-    let mut rng = rand::thread_rng();
+fn challenge12() {
+    info!("Running: challenge12");
     let mut key = [0u8; 16];
-    rng.fill_bytes(&mut key);
-
-    let key = key.as_ref();
-
-    if detect_block_size {
-        // Detect block size.
-        let mut block_size = 0;
-        for i in 1..64 {
-            debug!("Trying block size: {}", i);
-            let first = vec![0u8; i];
-
-            let ciphertext = oracle(
-                [first.as_slice(), first.as_slice()].concat().as_slice(),
-                key,
-            );
-
-            if ciphertext
-                .chunks(i)
-                .next()
-                .unwrap()
-                .eq(ciphertext.chunks(i).nth(1).unwrap())
-            {
-                debug!("Found block size: {}", i);
-                block_size = i;
-                break;
-            }
-        }
-
-        assert_eq!(block_size, 16);
-    }
-
-    let mut last_ciphertext = oracle(&[], key);
-    let mut offset = 0;
-
-    // Detect offset. For challeng 12, this will be 0. For challenge 14, this
-    // will be some random number depending on the random prefix.
-    for i in 1..16 {
-        let payload = vec![0u8; i];
-        let ciphertext = oracle(payload.as_slice(), key);
+    rand::thread_rng().fill_bytes(&mut key);
 
-        if last_ciphertext.chunks(16).next().unwrap() == ciphertext.chunks(16).next().unwrap() {
-            offset = i - 1;
-            debug!("Found offset: {}", offset);
-            break;
-        }
-        last_ciphertext = ciphertext;
-    }
-
-    // We don't know how large the target payload is, so start with some high
-    // multiple of 16.
-    let max_len = 128 + offset;
-    let mut learn_prefix = vec![65; max_len - 1]; // short 1-byte for the target char
-    let mut secret = vec![];
-
-    for i in (0..max_len).rev() {
-        debug!("Cracking byte: {}", i);
-        let target_ciphertext = oracle(&vec![65; i], key);
-
-        let mut matched = false;
-        for c in 0..255 {
-            let payload = [learn_prefix.as_ref(), [c].as_ref()].concat();
-            let ciphertext = oracle(payload.as_slice(), key);
-
-            if ciphertext[16..max_len] == target_ciphertext[16..max_len] {
-                matched = true;
-                secret.push(c);
-                learn_prefix = [learn_prefix[1..].to_vec(), [c].to_vec()].concat();
-                debug!("Cracked: [{}]", String::from_utf8_lossy(secret.as_ref()));
-                break;
-            }
-        }
+    let attack = EcbByteAtATime::new(|input: &[u8]| oracle12(input, &key)).unwrap();
+    assert_eq!(attack.block_size, 16);
+    assert_eq!(attack.prefix_len, 0);
 
-        if !matched {
-            // break;
-        }
-    }
-
-    assert!(String::from_utf8_lossy(secret.as_ref()).starts_with("Rollin'"));
+    let secret = attack.recover().unwrap();
+    debug!("Cracked: [{}]", String::from_utf8_lossy(&secret));
+    assert!(String::from_utf8_lossy(&secret).starts_with("Rollin'"));
+    assert_eq!(secret, secret12());
 }
 
 fn challenge13() {
@@ -280,31 +213,34 @@ fn challenge13() {
 }
 
 /// This is the same as oracle12, but with a random prefix prepended to the
-/// input.
-fn oracle14(input: &[u8], key: &[u8]) -> Vec<u8> {
-    let suffix = {
-        let suffix = r"
-    Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkg
-    aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq
-    dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg
-    YnkK
-    "
-        .split_whitespace()
-        .collect::<Vec<&str>>()
-        .join("");
-        general_purpose::STANDARD.decode(suffix).unwrap()
-    };
-
-    // Prepend a random prefix to the input. Let's just use 10 bytes here -- this
-    // is unknown to the attacker.
-    let prefix = vec![67u8; 10];
+/// input. The prefix is chosen along with the key, and is unknown to the attacker.
+fn oracle14(input: &[u8], prefix: &[u8], key: &[u8]) -> Vec<u8> {
+    let suffix = secret12();
 
     aes128_ecb_encrypt(
-        pkcs7_pad([&prefix, input, suffix.as_slice()].concat().as_slice(), 16).as_slice(),
+        pkcs7_pad([prefix, input, suffix.as_slice()].concat().as_slice(), 16).as_slice(),
         key,
     )
 }
 
+fn challenge14() {
+    info!("Running: challenge14");
+    let mut rng = rand::thread_rng();
+    let mut key = [0u8; 16];
+    rng.fill_bytes(&mut key);
+    let mut prefix = vec![0u8; rng.gen_range(0..=48)];
+    rng.fill_bytes(&mut prefix);
+
+    let attack = EcbByteAtATime::new(|input: &[u8]| oracle14(input, &prefix, &key)).unwrap();
+    assert_eq!(attack.block_size, 16);
+    debug!("Found prefix length: {}", attack.prefix_len);
+    assert_eq!(attack.prefix_len, prefix.len());
+
+    let secret = attack.recover().unwrap();
+    debug!("Cracked: [{}]", String::from_utf8_lossy(&secret));
+    assert_eq!(secret, secret12());
+}
+
 fn challenge15() {
     info!("Running: challenge15");
     let string = "ICE ICE BABY\x04\x04\x04\x04";
@@ -384,18 +320,14 @@ fn challenge16() {
     assert!(f2(&ct));
 }
 
-pub fn run(skip_slow_challenges: bool) {
+pub fn run() {
     info!("Running Set 2");
     challenge9();
     challenge10();
     challenge11();
-    if !skip_slow_challenges {
-        challenge12and14(Box::new(oracle12), false);
-    }
+    challenge12();
     challenge13();
-    if !skip_slow_challenges {
-        challenge12and14(Box::new(oracle14), false);
-    }
+    challenge14();
     challenge15();
     challenge16();
 }
@@ -0,0 +1,719 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use num_bigint::BigUint;
+use rand::{Rng, RngCore};
+use rayon::prelude::*;
+use tracing::*;
+
+use crate::aes::*;
+use crate::bignum::*;
+use crate::dh::*;
+use crate::net::*;
+use crate::rsa::*;
+use crate::srp::*;
+use crate::util::*;
+
+fn challenge33() {
+    info!("Running: challenge33");
+
+    // Start with small numbers so we can see the math at work.
+    for toy in [DhGroup::toy(), DhGroup::toy64()] {
+        let (a, b) = (toy.generate_keypair(), toy.generate_keypair());
+        debug!("toy (p = {}): A = {}, B = {}", toy.p, a.public, b.public);
+        assert_eq!(
+            toy.shared_secret(&a, &b.public),
+            toy.shared_secret(&b, &a.public)
+        );
+    }
+
+    // Now do it with bignums and the NIST group.
+    let group = DhGroup::nist();
+    let alice = group.generate_keypair();
+    let bob = group.generate_keypair();
+
+    let s1 = group.shared_secret(&alice, &bob.public);
+    let s2 = group.shared_secret(&bob, &alice.public);
+    assert_eq!(s1, s2);
+    assert_ne!(s1, BigUint::from(0u32));
+
+    // Turn the shared secret into a key, and make sure it works for both parties.
+    let keys = derive_keys(&s1);
+    let mut rng = rand::thread_rng();
+    let mut iv = [0u8; 16];
+    rng.fill_bytes(&mut iv);
+
+    let plaintext = "Diffie-Hellman says hi".as_bytes();
+    let ciphertext = aes128_cbc_encrypt(&pkcs7_pad(plaintext, 16), &keys.enc_key, &iv);
+    let decrypted = aes128_cbc_decrypt(&ciphertext, &derive_keys(&s2).enc_key, &iv);
+    assert_eq!(pkcs7_unpad(&decrypted).unwrap(), plaintext);
+}
+
+/// Messages exchanged by the echo protocol in challenge 34.
+#[derive(Clone, Debug)]
+enum EchoMessage {
+    /// A -> B: the group parameters and A's public key.
+    Params {
+        p: BigUint,
+        g: BigUint,
+        public: BigUint,
+    },
+    /// B -> A: B's public key.
+    PublicKey(BigUint),
+    /// Either way: AES-CBC(SHA1(s)[0:16], iv=random(16), msg) + iv.
+    Data(Vec<u8>),
+}
+
+/// Encrypts `plaintext` with AES-CBC under a random IV, and appends the IV to the ciphertext.
+fn encrypt_with_iv(plaintext: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut iv = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut iv);
+    [
+        aes128_cbc_encrypt(&pkcs7_pad(plaintext, 16), key, &iv),
+        iv.to_vec(),
+    ]
+    .concat()
+}
+
+/// Decrypts a ciphertext produced by `encrypt_with_iv`.
+fn decrypt_with_iv(data: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (ciphertext, iv) = data.split_at(data.len() - 16);
+    pkcs7_unpad(&aes128_cbc_decrypt(ciphertext, key, iv))
+}
+
+/// Runs A's side of the echo protocol: negotiate a key, send `message`, and return whatever B
+/// echoes back.
+fn echo_client(endpoint: Endpoint<EchoMessage>, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let group = DhGroup::nist();
+    let keypair = group.generate_keypair();
+    endpoint.send(EchoMessage::Params {
+        p: group.p.clone(),
+        g: group.g.clone(),
+        public: keypair.public.clone(),
+    })?;
+
+    let EchoMessage::PublicKey(other_public) = endpoint.recv()? else {
+        anyhow::bail!("expected public key");
+    };
+    let keys = derive_keys(&group.shared_secret(&keypair, &other_public));
+
+    endpoint.send(EchoMessage::Data(encrypt_with_iv(message, &keys.enc_key)))?;
+    let EchoMessage::Data(data) = endpoint.recv()? else {
+        anyhow::bail!("expected data");
+    };
+    decrypt_with_iv(&data, &keys.enc_key)
+}
+
+/// Runs B's side of the echo protocol: negotiate a key, then decrypt A's message and send it
+/// back re-encrypted under a fresh IV.
+fn echo_server(endpoint: Endpoint<EchoMessage>) -> anyhow::Result<()> {
+    let EchoMessage::Params { p, g, public } = endpoint.recv()? else {
+        anyhow::bail!("expected params");
+    };
+    let group = DhGroup::new(p, g);
+    let keypair = group.generate_keypair();
+    endpoint.send(EchoMessage::PublicKey(keypair.public.clone()))?;
+    let keys = derive_keys(&group.shared_secret(&keypair, &public));
+
+    let EchoMessage::Data(data) = endpoint.recv()? else {
+        anyhow::bail!("expected data");
+    };
+    let message = decrypt_with_iv(&data, &keys.enc_key)?;
+    endpoint.send(EchoMessage::Data(encrypt_with_iv(&message, &keys.enc_key)))
+}
+
+/// Runs the echo protocol between a client and a server thread, returning the echoed message.
+fn run_echo(a: Endpoint<EchoMessage>, b: Endpoint<EchoMessage>, message: &[u8]) -> Vec<u8> {
+    let server = thread::spawn(move || echo_server(b));
+    let echoed = echo_client(a, message).unwrap();
+    server.join().unwrap().unwrap();
+    echoed
+}
+
+fn challenge34() {
+    info!("Running: challenge34");
+
+    /// Replaces both public keys with p, which forces the shared secret to p^x mod p = 0.
+    #[derive(Default)]
+    struct ParameterInjection {
+        p: Option<BigUint>,
+        captured: Vec<Vec<u8>>,
+    }
+
+    impl Mitm<EchoMessage> for ParameterInjection {
+        fn intercept(&mut self, _: Direction, message: EchoMessage) -> Option<EchoMessage> {
+            match message {
+                EchoMessage::Params { p, g, .. } => {
+                    self.p = Some(p.clone());
+                    Some(EchoMessage::Params {
+                        public: p.clone(),
+                        p,
+                        g,
+                    })
+                }
+                EchoMessage::PublicKey(_) => Some(EchoMessage::PublicKey(self.p.clone()?)),
+                EchoMessage::Data(data) => {
+                    let keys = derive_keys(&BigUint::from(0u32));
+                    self.captured
+                        .push(decrypt_with_iv(&data, &keys.enc_key).unwrap());
+                    Some(EchoMessage::Data(data))
+                }
+            }
+        }
+    }
+
+    let message = "Did you stop? No, I just drove by".as_bytes();
+
+    // Make sure the protocol works without anyone in the middle.
+    let (a, b) = direct_link();
+    assert_eq!(run_echo(a, b, message), message);
+
+    // Now run it through the MITM, which should be able to read both messages without either
+    // party noticing.
+    let mitm = Arc::new(Mutex::new(ParameterInjection::default()));
+    let (a, b) = link(mitm.clone());
+    assert_eq!(run_echo(a, b, message), message);
+
+    let captured = &mitm.lock().unwrap().captured;
+    for plaintext in captured {
+        debug!("captured: {:?}", String::from_utf8_lossy(plaintext));
+    }
+    assert_eq!(captured, &[message.to_vec(), message.to_vec()]);
+}
+
+/// Messages exchanged by the negotiated-group echo protocol in challenge 35.
+#[derive(Clone, Debug)]
+enum NegotiatedMessage {
+    /// A -> B: the proposed group parameters.
+    Negotiate { p: BigUint, g: BigUint },
+    /// B -> A: the parameters are accepted.
+    Ack,
+    /// Either way: the sender's public key.
+    PublicKey(BigUint),
+    /// Either way: AES-CBC(SHA1(s)[0:16], iv=random(16), msg) + iv.
+    Data(Vec<u8>),
+}
+
+/// Like `echo_client`, but the group is negotiated up front and acknowledged by B before any
+/// keys are exchanged.
+fn negotiated_echo_client(
+    endpoint: Endpoint<NegotiatedMessage>,
+    message: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let group = DhGroup::nist();
+    endpoint.send(NegotiatedMessage::Negotiate {
+        p: group.p.clone(),
+        g: group.g.clone(),
+    })?;
+    let NegotiatedMessage::Ack = endpoint.recv()? else {
+        anyhow::bail!("expected ack");
+    };
+
+    let keypair = group.generate_keypair();
+    endpoint.send(NegotiatedMessage::PublicKey(keypair.public.clone()))?;
+    let NegotiatedMessage::PublicKey(other_public) = endpoint.recv()? else {
+        anyhow::bail!("expected public key");
+    };
+    let keys = derive_keys(&group.shared_secret(&keypair, &other_public));
+
+    endpoint.send(NegotiatedMessage::Data(encrypt_with_iv(
+        message,
+        &keys.enc_key,
+    )))?;
+    let NegotiatedMessage::Data(data) = endpoint.recv()? else {
+        anyhow::bail!("expected data");
+    };
+    decrypt_with_iv(&data, &keys.enc_key)
+}
+
+/// Like `echo_server`, but uses whatever group A negotiated.
+fn negotiated_echo_server(endpoint: Endpoint<NegotiatedMessage>) -> anyhow::Result<()> {
+    let NegotiatedMessage::Negotiate { p, g } = endpoint.recv()? else {
+        anyhow::bail!("expected negotiate");
+    };
+    let group = DhGroup::new(p, g);
+    endpoint.send(NegotiatedMessage::Ack)?;
+
+    let NegotiatedMessage::PublicKey(other_public) = endpoint.recv()? else {
+        anyhow::bail!("expected public key");
+    };
+    let keypair = group.generate_keypair();
+    endpoint.send(NegotiatedMessage::PublicKey(keypair.public.clone()))?;
+    let keys = derive_keys(&group.shared_secret(&keypair, &other_public));
+
+    let NegotiatedMessage::Data(data) = endpoint.recv()? else {
+        anyhow::bail!("expected data");
+    };
+    let message = decrypt_with_iv(&data, &keys.enc_key)?;
+    endpoint.send(NegotiatedMessage::Data(encrypt_with_iv(
+        &message,
+        &keys.enc_key,
+    )))
+}
+
+fn challenge35() {
+    info!("Running: challenge35");
+
+    #[derive(Clone, Copy, Debug)]
+    enum MaliciousG {
+        One,
+        P,
+        PMinusOne,
+    }
+
+    /// Tampers with the negotiated g on its way to B, so B's public key becomes predictable, and
+    /// replaces A's public key so that B's shared secret is predictable too. The MITM then knows
+    /// both secrets, and can transparently decrypt and re-encrypt traffic in both directions.
+    struct GInjection {
+        g: MaliciousG,
+        p: BigUint,
+        // The secret A derives from B's (tampered) public key. For g = p - 1, this depends on
+        // the parity of both private keys, and is only known once A's first message arrives.
+        a_secrets: Vec<BigUint>,
+        // The secret B derives from the public key we substituted for A's.
+        b_secret: BigUint,
+        captured: Vec<Vec<u8>>,
+    }
+
+    impl GInjection {
+        fn new(g: MaliciousG) -> Self {
+            Self {
+                g,
+                p: BigUint::from(0u32),
+                a_secrets: vec![],
+                b_secret: BigUint::from(0u32),
+                captured: vec![],
+            }
+        }
+
+        fn forged_g(&self) -> BigUint {
+            match self.g {
+                MaliciousG::One => BigUint::from(1u32),
+                MaliciousG::P => self.p.clone(),
+                MaliciousG::PMinusOne => &self.p - 1u32,
+            }
+        }
+    }
+
+    impl Mitm<NegotiatedMessage> for GInjection {
+        fn intercept(
+            &mut self,
+            direction: Direction,
+            message: NegotiatedMessage,
+        ) -> Option<NegotiatedMessage> {
+            let one = BigUint::from(1u32);
+            match (direction, message) {
+                (Direction::AtoB, NegotiatedMessage::Negotiate { p, .. }) => {
+                    self.p = p.clone();
+                    Some(NegotiatedMessage::Negotiate {
+                        p,
+                        g: self.forged_g(),
+                    })
+                }
+                (Direction::AtoB, NegotiatedMessage::PublicKey(_)) => {
+                    // Send B the forged g in place of A's public key, so it computes
+                    // g'^b, which we've already worked out below.
+                    let (forged, b_secret) = match self.g {
+                        MaliciousG::One => (one.clone(), one),
+                        MaliciousG::P => (self.p.clone(), BigUint::from(0u32)),
+                        MaliciousG::PMinusOne => (one.clone(), one),
+                    };
+                    self.b_secret = b_secret;
+                    Some(NegotiatedMessage::PublicKey(forged))
+                }
+                (Direction::BtoA, NegotiatedMessage::PublicKey(public)) => {
+                    // B's public key is g'^b, and A raises it to its own private key a.
+                    //
+                    //   g' = 1:     B = 1, so s = 1
+                    //   g' = p:     B = 0, so s = 0
+                    //   g' = p - 1: B = 1 if b is even (s = 1), or p - 1 if b is odd, in which
+                    //               case s = 1 if a is even, or p - 1 if a is odd.
+                    self.a_secrets = match self.g {
+                        MaliciousG::One => vec![one],
+                        MaliciousG::P => vec![BigUint::from(0u32)],
+                        MaliciousG::PMinusOne if public == one => vec![one],
+                        MaliciousG::PMinusOne => vec![one, &self.p - 1u32],
+                    };
+                    Some(NegotiatedMessage::PublicKey(public))
+                }
+                (Direction::AtoB, NegotiatedMessage::Data(data)) => {
+                    // Try each candidate secret, and keep the one that decrypts with valid
+                    // padding. A wrong key gives valid padding about 1 in 256 times, so also
+                    // check that the plaintext looks like text.
+                    let (secret, plaintext) = self.a_secrets.iter().find_map(|secret| {
+                        let key = derive_keys(secret).enc_key;
+                        decrypt_with_iv(&data, &key)
+                            .ok()
+                            .filter(|plaintext| plaintext.is_ascii())
+                            .map(|plaintext| (secret.clone(), plaintext))
+                    })?;
+                    debug!("{:?}: A's shared secret is {}", self.g, secret);
+                    self.a_secrets = vec![secret];
+
+                    let key = derive_keys(&self.b_secret).enc_key;
+                    let data = encrypt_with_iv(&plaintext, &key);
+                    self.captured.push(plaintext);
+                    Some(NegotiatedMessage::Data(data))
+                }
+                (Direction::BtoA, NegotiatedMessage::Data(data)) => {
+                    let key = derive_keys(&self.b_secret).enc_key;
+                    let plaintext = decrypt_with_iv(&data, &key).ok()?;
+
+                    let key = derive_keys(&self.a_secrets[0]).enc_key;
+                    let data = encrypt_with_iv(&plaintext, &key);
+                    self.captured.push(plaintext);
+                    Some(NegotiatedMessage::Data(data))
+                }
+                (_, message) => Some(message),
+            }
+        }
+    }
+
+    let message = "Wu-Tang Clan ain't nuthing ta f' wit".as_bytes();
+
+    // Make sure the protocol works without anyone in the middle.
+    let (a, b) = direct_link();
+    let server = thread::spawn(move || negotiated_echo_server(b));
+    assert_eq!(negotiated_echo_client(a, message).unwrap(), message);
+    server.join().unwrap().unwrap();
+
+    // The p - 1 case depends on the parity of the private keys, so run it a few times to make
+    // sure we hit all the combinations.
+    let attacks = [MaliciousG::One, MaliciousG::P]
+        .into_iter()
+        .chain([MaliciousG::PMinusOne; 8]);
+
+    for g in attacks {
+        let mitm = Arc::new(Mutex::new(GInjection::new(g)));
+        let (a, b) = link(mitm.clone());
+        let server = thread::spawn(move || negotiated_echo_server(b));
+        assert_eq!(negotiated_echo_client(a, message).unwrap(), message);
+        server.join().unwrap().unwrap();
+
+        let captured = &mitm.lock().unwrap().captured;
+        assert_eq!(captured, &[message.to_vec(), message.to_vec()]);
+    }
+}
+
+/// Messages exchanged during an SRP login.
+#[derive(Clone, Debug)]
+enum SrpMessage {
+    /// C -> S: the user's email and the client's public key.
+    Hello { email: String, a_pub: BigUint },
+    /// S -> C: the user's salt and the server's public key.
+    Challenge { salt: Vec<u8>, b_pub: BigUint },
+    /// S -> C: like `Challenge`, but for simplified SRP, which also sends u.
+    SimplifiedChallenge {
+        salt: Vec<u8>,
+        b_pub: BigUint,
+        u: BigUint,
+    },
+    /// C -> S: HMAC-SHA256(K, salt).
+    Proof(Vec<u8>),
+    /// S -> C: whether the login succeeded.
+    LoginResult(bool),
+}
+
+/// Handles a single SRP login on `endpoint`, returning whether it succeeded.
+fn srp_serve_login(endpoint: Endpoint<SrpMessage>, server: &SrpServer) -> anyhow::Result<bool> {
+    let SrpMessage::Hello { email, a_pub } = endpoint.recv()? else {
+        anyhow::bail!("expected hello");
+    };
+    let session = server.begin(&email, &a_pub)?;
+    endpoint.send(SrpMessage::Challenge {
+        salt: session.salt.clone(),
+        b_pub: session.b_pub.clone(),
+    })?;
+
+    let SrpMessage::Proof(proof) = endpoint.recv()? else {
+        anyhow::bail!("expected proof");
+    };
+    let ok = session.verify(&proof);
+    endpoint.send(SrpMessage::LoginResult(ok))?;
+    Ok(ok)
+}
+
+/// Logs in to the server on `endpoint`, returning whether the server accepted the proof.
+fn srp_login(endpoint: Endpoint<SrpMessage>, client: &SrpClient) -> anyhow::Result<bool> {
+    let (email, a_pub) = client.hello();
+    endpoint.send(SrpMessage::Hello { email, a_pub })?;
+
+    let SrpMessage::Challenge { salt, b_pub } = endpoint.recv()? else {
+        anyhow::bail!("expected challenge");
+    };
+    endpoint.send(SrpMessage::Proof(client.proof(&salt, &b_pub)))?;
+
+    let SrpMessage::LoginResult(ok) = endpoint.recv()? else {
+        anyhow::bail!("expected login result");
+    };
+    Ok(ok)
+}
+
+/// Like `srp_serve_login`, but for simplified SRP.
+fn simplified_srp_serve_login(
+    endpoint: Endpoint<SrpMessage>,
+    server: &SrpServer,
+) -> anyhow::Result<bool> {
+    let SrpMessage::Hello { email, a_pub } = endpoint.recv()? else {
+        anyhow::bail!("expected hello");
+    };
+    let session = server.begin_simplified(&email, &a_pub)?;
+    endpoint.send(SrpMessage::SimplifiedChallenge {
+        salt: session.salt.clone(),
+        b_pub: session.b_pub.clone(),
+        u: session.u.clone(),
+    })?;
+
+    let SrpMessage::Proof(proof) = endpoint.recv()? else {
+        anyhow::bail!("expected proof");
+    };
+    let ok = session.verify(&proof);
+    endpoint.send(SrpMessage::LoginResult(ok))?;
+    Ok(ok)
+}
+
+/// Like `srp_login`, but for simplified SRP.
+fn simplified_srp_login(
+    endpoint: Endpoint<SrpMessage>,
+    client: &SrpClient,
+) -> anyhow::Result<bool> {
+    let (email, a_pub) = client.hello();
+    endpoint.send(SrpMessage::Hello { email, a_pub })?;
+
+    let SrpMessage::SimplifiedChallenge { salt, b_pub, u } = endpoint.recv()? else {
+        anyhow::bail!("expected challenge");
+    };
+    endpoint.send(SrpMessage::Proof(
+        client.proof_simplified(&salt, &b_pub, &u),
+    ))?;
+
+    let SrpMessage::LoginResult(ok) = endpoint.recv()? else {
+        anyhow::bail!("expected login result");
+    };
+    Ok(ok)
+}
+
+fn challenge36() {
+    info!("Running: challenge36");
+
+    let email = "ghostface@killah.com";
+    let mut server = SrpServer::new(SrpParams::nist());
+    server.register(email, "supreme clientele");
+    let server = Arc::new(server);
+
+    let try_login = |password: &str| {
+        let (c, s) = direct_link();
+        let server = server.clone();
+        let handle = thread::spawn(move || srp_serve_login(s, &server));
+
+        let client = SrpClient::new(SrpParams::nist(), email, password);
+        let ok = srp_login(c, &client).unwrap();
+        assert_eq!(handle.join().unwrap().unwrap(), ok);
+        ok
+    };
+
+    assert!(try_login("supreme clientele"));
+    assert!(!try_login("ironman"));
+}
+
+fn challenge37() {
+    info!("Running: challenge37");
+
+    let email = "ghostface@killah.com";
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone());
+    let mut hardened = SrpServer::new_hardened(params.clone());
+    server.register(email, "supreme clientele");
+    hardened.register(email, "supreme clientele");
+    let (server, hardened) = (Arc::new(server), Arc::new(hardened));
+
+    // Log in without the password by sending A = 0, N, 2N, ..., which makes the server's secret
+    // S = (A * v^u)^b mod N = 0. The proof is then just HMAC(SHA256(0), salt).
+    let zero_key_login = |server: &Arc<SrpServer>, a_pub: BigUint| {
+        let (c, s) = direct_link();
+        let server = server.clone();
+        let handle = thread::spawn(move || srp_serve_login(s, &server));
+
+        let ok = (|| {
+            c.send(SrpMessage::Hello {
+                email: email.to_string(),
+                a_pub,
+            })?;
+            let SrpMessage::Challenge { salt, .. } = c.recv()? else {
+                anyhow::bail!("expected challenge");
+            };
+            c.send(SrpMessage::Proof(session_proof(
+                &BigUint::from(0u32),
+                &salt,
+            )))?;
+            let SrpMessage::LoginResult(ok) = c.recv()? else {
+                anyhow::bail!("expected login result");
+            };
+            Ok(ok)
+        })();
+
+        // The hardened server bails out and hangs up, so treat any error as a rejection.
+        let _ = handle.join().unwrap();
+        ok.unwrap_or(false)
+    };
+
+    for i in 0..4u32 {
+        let a_pub = &params.n * i;
+        assert!(zero_key_login(&server, a_pub.clone()));
+        assert!(!zero_key_login(&hardened, a_pub));
+    }
+}
+
+fn challenge38() {
+    info!("Running: challenge38");
+
+    let words = std::fs::read_to_string("data/words.txt").unwrap();
+    let words = words.lines().collect::<Vec<_>>();
+    let password = words[rand::thread_rng().gen_range(0..words.len())];
+
+    let email = "ghostface@killah.com";
+    let params = SrpParams::nist();
+    let mut server = SrpServer::new(params.clone());
+    server.register(email, password);
+
+    // Make sure the protocol works with the real server.
+    let (c, s) = direct_link();
+    let handle = thread::spawn(move || simplified_srp_serve_login(s, &server));
+    let client = SrpClient::new(params.clone(), email, password);
+    assert!(simplified_srp_login(c, &client).unwrap());
+    assert!(handle.join().unwrap().unwrap());
+
+    // Now pose as the server. We pick b = 1 (so B = g) and u = 1, and capture the client's
+    // public key and proof.
+    let (c, s) = direct_link();
+    let g = params.g.clone();
+    let handle = thread::spawn(move || -> anyhow::Result<(BigUint, Vec<u8>, Vec<u8>)> {
+        let SrpMessage::Hello { a_pub, .. } = s.recv()? else {
+            anyhow::bail!("expected hello");
+        };
+        let salt = vec![0u8; 16];
+        s.send(SrpMessage::SimplifiedChallenge {
+            salt: salt.clone(),
+            b_pub: g,
+            u: BigUint::from(1u32),
+        })?;
+        let SrpMessage::Proof(proof) = s.recv()? else {
+            anyhow::bail!("expected proof");
+        };
+        s.send(SrpMessage::LoginResult(false))?;
+        Ok((a_pub, salt, proof))
+    });
+
+    let client = SrpClient::new(params.clone(), email, password);
+    assert!(!simplified_srp_login(c, &client).unwrap());
+    let (a_pub, salt, proof) = handle.join().unwrap().unwrap();
+
+    // Crack the password offline. With b = 1 and u = 1, the client's secret is
+    // S = B^(a + ux) = g^a * g^x = A * v mod N, so each guess costs a single modexp.
+    let attempts = AtomicU64::new(0);
+    let start = Instant::now();
+    let cracked = words.par_iter().find_any(|word| {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        let v = modexp(&params.g, &private_key(&salt, word), &params.n);
+        session_proof(&(&a_pub * v % &params.n), &salt) == proof
+    });
+
+    let attempts = attempts.into_inner();
+    let elapsed = start.elapsed().as_secs_f64();
+    info!(
+        "Cracked password {:?} in {} attempts ({:.0} attempts/sec across {} threads)",
+        cracked,
+        attempts,
+        attempts as f64 / elapsed,
+        rayon::current_num_threads()
+    );
+    assert_eq!(cracked, Some(&password));
+}
+
+fn challenge39() {
+    info!("Running: challenge39");
+
+    assert_eq!(
+        modinv(&BigUint::from(17u32), &BigUint::from(3120u32)),
+        Some(BigUint::from(2753u32))
+    );
+
+    let message = "Shaolin shadowboxing and the Wu-Tang sword style".as_bytes();
+    for (bits, e) in [(1024, 3), (2048, 65537)] {
+        let key = RsaPrivateKey::generate(bits, e);
+        debug!("generated {}-bit key with e = {}", bits, e);
+
+        let ciphertext = key.public_key().encrypt(message);
+        assert_ne!(ciphertext, message);
+        assert_eq!(key.decrypt(&ciphertext), message);
+
+        // Decrypting the slow way, without the CRT parameters, should give the same result.
+        let m = modexp(&from_bytes(&ciphertext), &key.d, &key.n);
+        assert_eq!(to_bytes(&m), message);
+    }
+}
+
+/// Recovers a message that was encrypted without padding under `e` different public keys, all
+/// with the same small exponent `e`. Each intercept is a public key and the ciphertext for it.
+///
+/// Since c_i = m^e mod n_i, the CRT gives us m^e mod (n_1 * ... * n_e). And since m is smaller
+/// than every n_i, m^e is smaller than their product, so it's not reduced at all, and we can
+/// just take the integer e-th root.
+fn rsa_broadcast_attack(intercepts: &[(RsaPublicKey, BigUint)]) -> Option<BigUint> {
+    let e = &intercepts[0].0.e;
+    assert!(intercepts.iter().all(|(key, _)| &key.e == e));
+    assert_eq!(BigUint::from(intercepts.len()), *e);
+
+    let (moduli, residues): (Vec<_>, Vec<_>) = intercepts
+        .iter()
+        .map(|(key, c)| (key.n.clone(), c.clone()))
+        .unzip();
+
+    let m_e = crt(&residues, &moduli)?;
+    exact_nth_root(&m_e, intercepts.len() as u32)
+}
+
+fn challenge40() {
+    info!("Running: challenge40");
+
+    // Long enough that m^e wraps around each modulus, so simply taking the e-th root of a
+    // single ciphertext won't work.
+    let message = "Protect ya neck, 'cause the Wu-Tang Clan is comin' thru".as_bytes();
+    let m = from_bytes(message);
+
+    for e in [3, 5, 7] {
+        // Encrypt the same message under e different keys. Fresh random primes are coprime
+        // to each other, which is all the CRT needs.
+        let intercepts = (0..e)
+            .map(|_| {
+                let key = RsaPrivateKey::generate(512, e).public_key();
+                let c = key.encrypt_int(&m);
+                (key, c)
+            })
+            .collect::<Vec<_>>();
+
+        assert!(intercepts.iter().all(|(key, _)| m.pow(e) > key.n));
+
+        let recovered = rsa_broadcast_attack(&intercepts).unwrap();
+        debug!(
+            "e = {}: {:?}",
+            e,
+            String::from_utf8_lossy(&to_bytes(&recovered))
+        );
+        assert_eq!(to_bytes(&recovered), message);
+    }
+}
+
+pub fn run() {
+    info!("Running Set 5");
+    challenge33();
+    challenge34();
+    challenge35();
+    challenge36();
+    challenge37();
+    challenge38();
+    challenge39();
+    challenge40();
+}
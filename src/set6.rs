@@ -0,0 +1,564 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose, Engine};
+use num_bigint::{BigUint, RandBigInt};
+use sha2::{Digest, Sha256};
+use tracing::*;
+
+use crate::bignum::*;
+use crate::dsa::*;
+use crate::rsa::*;
+
+/// A server that decrypts RSA ciphertexts for anyone, but only once: it remembers the hash of
+/// every ciphertext it has seen, and refuses to decrypt them again.
+struct OnceDecryptor {
+    key: RsaPrivateKey,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl OnceDecryptor {
+    fn new(key: RsaPrivateKey) -> Self {
+        Self {
+            key,
+            seen: HashSet::new(),
+        }
+    }
+
+    fn decrypt(&mut self, c: &BigUint) -> Option<BigUint> {
+        let hash = Sha256::digest(to_bytes(c)).to_vec();
+        if !self.seen.insert(hash) {
+            debug!("refusing to decrypt ciphertext twice");
+            return None;
+        }
+
+        Some(self.key.decrypt_int(c))
+    }
+}
+
+fn challenge41() {
+    info!("Running: challenge41");
+
+    let mut server = OnceDecryptor::new(RsaPrivateKey::generate(1024, 65537));
+    let public = server.key.public_key();
+
+    // Capture a ciphertext, which the victim has already had decrypted.
+    let message = r#"{time: 1356304276, social: "555-55-5555"}"#.as_bytes();
+    let c = public.encrypt_int(&from_bytes(message));
+    assert_eq!(to_bytes(&server.decrypt(&c).unwrap()), message);
+    assert!(server.decrypt(&c).is_none());
+
+    // Blind it with a random s, so the server sees a brand new ciphertext that decrypts to
+    // s * m mod n. Then divide out s to get m.
+    let n = &public.n;
+    let s = loop {
+        let s = rand::thread_rng().gen_biguint_range(&BigUint::from(2u32), n);
+        if modinv(&s, n).is_some() {
+            break s;
+        }
+    };
+
+    let blinded = public.blinded_query(&c, &s, |c| server.decrypt(c)).unwrap();
+    let recovered = blinded * modinv(&s, n).unwrap() % n;
+
+    debug!(
+        "recovered: {:?}",
+        String::from_utf8_lossy(&to_bytes(&recovered))
+    );
+    assert_eq!(to_bytes(&recovered), message);
+}
+
+/// Forges a PKCS#1 v1.5 signature for `message` that passes a sloppy verifier (one that
+/// doesn't check that the digest is right-justified) for any key with e = 3 and a large enough
+/// modulus. No private key required.
+fn forge_pkcs1v15_e3(public: &RsaPublicKey, hash: HashAlg, message: &[u8]) -> Vec<u8> {
+    assert_eq!(public.e, BigUint::from(3u32));
+    let k = public.size();
+
+    // Build a block with as little padding as possible, followed by the DigestInfo and hash,
+    // then fill the rest with garbage (0xff). Taking the floor cube root only disturbs the low
+    // bytes of the garbage, so cubing the result gives back our prefix.
+    //
+    //   00 01 FF 00 DigestInfo Hash(message) FF FF ... FF
+    let prefix = [
+        &[0x00, 0x01, 0xff, 0x00],
+        hash.digest_info_prefix(),
+        &hash.digest(message),
+    ]
+    .concat();
+    let block = [prefix, vec![0xff; k]].concat();
+    let forged = cube_root(&from_bytes(&block[..k]));
+
+    to_bytes_padded(&forged, k)
+}
+
+fn challenge42() {
+    info!("Running: challenge42");
+    let message = "hi mom".as_bytes();
+
+    // Cubing the forged root only preserves roughly the top third of the block, so SHA-256's
+    // longer DigestInfo and digest need a bigger modulus than SHA-1's.
+    for (bits, hash) in [(1024, HashAlg::Sha1), (2048, HashAlg::Sha256)] {
+        let key = RsaPrivateKey::generate(bits, 3);
+        let public = key.public_key();
+
+        // Legit signatures pass both verifiers.
+        let signature = key.sign_pkcs1v15(hash, message);
+        assert!(public.verify_pkcs1v15_sloppy(hash, message, &signature));
+        assert!(public.verify_pkcs1v15(hash, message, &signature));
+
+        let forged = forge_pkcs1v15_e3(&public, hash, message);
+        debug!("forged {:?} signature: {}", hash, hex::encode(&forged));
+
+        assert!(public.verify_pkcs1v15_sloppy(hash, message, &forged));
+        assert!(!public.verify_pkcs1v15(hash, message, &forged));
+    }
+}
+
+/// Returns the fingerprint the cryptopals DSA challenges use to check a recovered private key:
+/// SHA1 of its lowercase hex representation.
+fn dsa_key_fingerprint(x: &BigUint) -> String {
+    hex::encode(sha1::Sha1::digest(to_hex(x)))
+}
+
+fn challenge43() {
+    info!("Running: challenge43");
+
+    let params = DsaParams::cryptopals();
+    let public = DsaPublicKey {
+        params: params.clone(),
+        y: from_hex(
+            r"
+            84ad4719d044495496a3201c8ff484feb45b962e7302e56a392aee4abab3e4bd
+            ebf2955b4736012f21a08084056b19bcd7fee56048e004e44984e2f411788efd
+            c837a0d2e5abb7b555039fd243ac01f0fb2ed1dec568280ce678e931868d23eb
+            095fde9d3779191b8c0299d6e07bbb283e6633451e535c45513b2d33c99ea17",
+        ),
+    };
+
+    let message = "For those that envy a MC it can be hazardous to your health\n\
+        So be friendly, a matter of life and death, just like a etch-a-sketch\n"
+        .as_bytes();
+    let h = hash_message(message);
+    assert_eq!(to_hex(&h), "d2d0714f014a9784047eaeccf956520045c45265");
+
+    let sig = DsaSignature {
+        r: "548099063082341131477253921760299949438196259240"
+            .parse()
+            .unwrap(),
+        s: "857042759984254168557880549501802188789837994940"
+            .parse()
+            .unwrap(),
+    };
+    assert!(public.verify(message, &sig));
+
+    // Make sure signing and recovering x from a known nonce work with our own key first.
+    let key = DsaPrivateKey::generate(params.clone());
+    assert!(key.public_key().verify(message, &key.sign(message)));
+    let k = BigUint::from(31337u32);
+    let our_sig = key.sign_with_k(message, &k).unwrap();
+    assert_eq!(recover_x(&params, &h, &our_sig, &k), Some(key.x.clone()));
+
+    // The nonce was picked from 0..2^16, so try them all. Find the k for which
+    // r = (g^k mod p) mod q, stepping g^k along by one multiplication at a time, then check the
+    // x it gives us against y.
+    let mut g_k = BigUint::from(1u32);
+    let mut x = None;
+    for k in 0u32..1 << 16 {
+        if &g_k % &params.q == sig.r {
+            let candidate = recover_x(&params, &h, &sig, &BigUint::from(k)).unwrap();
+            if modexp(&params.g, &candidate, &params.p) == public.y {
+                x = Some(candidate);
+                break;
+            }
+        }
+        g_k = g_k * &params.g % &params.p;
+    }
+    let x = x.unwrap();
+
+    debug!("recovered x: {}", to_hex(&x));
+    assert_eq!(
+        dsa_key_fingerprint(&x),
+        "0954edd5e0afe5542a4adf012611a91912a3ec16"
+    );
+}
+
+fn challenge44() {
+    info!("Running: challenge44");
+
+    let params = DsaParams::cryptopals();
+
+    let y = from_hex(
+        r"
+        2d026f4bf30195ede3a088da85e398ef869611d0f68f0713d51c9c1a3a26c951
+        05d915e2d8cdf26d056b86b8a7b85519b1c23cc3ecdc6062650462e3063bd179
+        c2a6581519f674a61f1d89a1fff27171ebc1b93d4dc57bceb7ae2430f98a6a4d
+        83d8279ee65d71c1203d2c96d65ebbf7cce9d32971c3de5084cce04a2e147821",
+    );
+
+    // Each entry is a msg line, then s, r, and m (the SHA1 of msg, in hex). Our copy of the
+    // challenge's file only has the entries we could check against the published key, and only
+    // some of those come with their msg line, so we check m against msg where we can.
+    let data = std::fs::read_to_string("data/44.txt").unwrap();
+    let mut entries = vec![];
+    let (mut msg, mut s, mut r) = (None, None, None);
+    for line in data.lines() {
+        let (name, value) = line.split_once(": ").unwrap();
+        match name {
+            "msg" => msg = Some(value),
+            "s" => s = Some(value.parse().unwrap()),
+            "r" => r = Some(value.parse().unwrap()),
+            "m" => {
+                let m = from_hex(value);
+                if let Some(msg) = msg.take() {
+                    assert_eq!(hash_message(msg.as_bytes()), m);
+                }
+                let sig = DsaSignature {
+                    s: s.take().unwrap(),
+                    r: r.take().unwrap(),
+                };
+                entries.push((m, sig));
+            }
+            _ => panic!("unexpected line in data/44.txt: {}", line),
+        }
+    }
+
+    // Signatures with the same r share a nonce, which gives away the key.
+    let mut recovered = None;
+    for (i, (m1, sig1)) in entries.iter().enumerate() {
+        for (m2, sig2) in entries.iter().skip(i + 1) {
+            let Some(k) = recover_k_from_repeated_nonce(&params, (m1, sig1), (m2, sig2)) else {
+                continue;
+            };
+            let x = recover_x(&params, m1, sig1, &k).unwrap();
+            debug!("repeated nonce: k = {}, x = {}", to_hex(&k), to_hex(&x));
+
+            assert_eq!(modexp(&params.g, &x, &params.p), y);
+            recovered = Some(x);
+        }
+    }
+
+    assert_eq!(
+        dsa_key_fingerprint(&recovered.unwrap()),
+        "ca8f6f7c66fa362d40760d135b763eb8527d3d52"
+    );
+}
+
+fn challenge45() {
+    info!("Running: challenge45");
+
+    let params = DsaParams::cryptopals();
+    let key = DsaPrivateKey::generate(params.clone());
+    let messages = ["Hello, world".as_bytes(), "Goodbye, world".as_bytes()];
+
+    // With g = 0, g^u1 mod p is 0, so v = 0 no matter what y is. A signature with r = 0 and
+    // any s then verifies any message, as long as the verifier doesn't insist on 0 < r < q.
+    let tampered = DsaPublicKey {
+        params: DsaParams {
+            g: BigUint::from(0u32),
+            ..params.clone()
+        },
+        y: key.y.clone(),
+    };
+    let magic = DsaSignature {
+        r: BigUint::from(0u32),
+        s: rand::thread_rng().gen_biguint_range(&BigUint::from(1u32), &params.q),
+    };
+    for message in messages {
+        assert!(tampered.verify_unchecked(message, &magic));
+        assert!(!tampered.verify(message, &magic));
+    }
+
+    // With g = p + 1, g^u1 = 1 mod p, so v = y^u2 mod p mod q. Picking any z, and setting
+    // r = (y^z mod p) mod q and s = r / z mod q makes u2 = r / s = z, so v = r.
+    let tampered = DsaPublicKey {
+        params: DsaParams {
+            g: &params.p + 1u32,
+            ..params.clone()
+        },
+        y: key.y.clone(),
+    };
+    let q = &params.q;
+    let z = rand::thread_rng().gen_biguint_range(&BigUint::from(1u32), q);
+    let r = modexp(&key.y, &z, &params.p) % q;
+    let s = &r * modinv(&z, q).unwrap() % q;
+    let magic = DsaSignature { r, s };
+    for message in messages {
+        assert!(tampered.verify_unchecked(message, &magic));
+        assert!(!tampered.verify(message, &magic));
+    }
+
+    // Legit signatures still verify with the hardened verifier.
+    let sig = key.sign(messages[0]);
+    assert!(key.public_key().verify(messages[0], &sig));
+}
+
+/// Recovers the plaintext of `c` given an oracle that reveals whether a ciphertext decrypts to
+/// an even number. If `hollywood` is set, the partially decrypted plaintext is logged (at debug
+/// level) after every query.
+///
+/// Doubling the plaintext (by blinding with s = 2) wraps it around the odd modulus exactly when
+/// it's bigger than n/2, which makes the result odd. So each query halves the range the
+/// plaintext could be in, and log2(n) queries pin it down.
+fn rsa_parity_attack(
+    public: &RsaPublicKey,
+    c: &BigUint,
+    oracle: impl Fn(&BigUint) -> bool,
+    hollywood: bool,
+) -> BigUint {
+    let n = &public.n;
+
+    // The plaintext is within [n * lo / den, n * hi / den].
+    let (mut lo, mut hi, mut den) = (
+        BigUint::from(0u32),
+        BigUint::from(1u32),
+        BigUint::from(1u32),
+    );
+    for i in 1..=n.bits() {
+        let s = BigUint::from(1u32) << i;
+        let is_even = public.blinded_query(c, &s, &oracle);
+
+        lo <<= 1;
+        hi <<= 1;
+        den <<= 1;
+        if is_even {
+            hi -= 1u32;
+        } else {
+            lo += 1u32;
+        }
+
+        if hollywood {
+            debug!(
+                "{:?}",
+                String::from_utf8_lossy(&to_bytes(&(n * &hi / &den)))
+            );
+        }
+    }
+
+    n * hi / den
+}
+
+fn challenge46() {
+    info!("Running: challenge46");
+
+    let key = RsaPrivateKey::generate(1024, 65537);
+    let public = key.public_key();
+    let is_even = |c: &BigUint| !key.decrypt_int(c).bit(0);
+
+    let message = general_purpose::STANDARD
+        .decode(
+            "VGhhdCdzIHdoeSBJIGZvdW5kIHlvdSBkb24ndCBwbGF5IGFyb3VuZCB3aXRoIHRoZSBGdW5reSBDb2xkIE1lZGluYQ==",
+        )
+        .unwrap();
+    let c = public.encrypt_int(&from_bytes(&message));
+
+    let recovered = rsa_parity_attack(&public, &c, is_even, true);
+    debug!(
+        "recovered: {:?}",
+        String::from_utf8_lossy(&to_bytes(&recovered))
+    );
+    assert_eq!(to_bytes(&recovered), message);
+}
+
+/// How much of the PKCS#1 v1.5 padding a padding oracle checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OracleStrictness {
+    /// Only checks that the plaintext starts with 00 02.
+    Header,
+    /// Also checks for at least 8 bytes of padding followed by a 00 separator.
+    Full,
+}
+
+/// A server that decrypts PKCS#1 v1.5 padded messages and leaks whether the padding was valid.
+struct PaddingOracle {
+    key: RsaPrivateKey,
+    strictness: OracleStrictness,
+    queries: Cell<u64>,
+}
+
+impl PaddingOracle {
+    fn new(key: RsaPrivateKey, strictness: OracleStrictness) -> Self {
+        Self {
+            key,
+            strictness,
+            queries: Cell::new(0),
+        }
+    }
+
+    fn is_conforming(&self, c: &BigUint) -> bool {
+        self.queries.set(self.queries.get() + 1);
+        let k = self.key.public_key().size();
+        let block = to_bytes_padded(&self.key.decrypt_int(c), k);
+        match self.strictness {
+            OracleStrictness::Header => block.starts_with(&[0x00, 0x02]),
+            OracleStrictness::Full => pkcs1v15_unpad(&block).is_ok(),
+        }
+    }
+}
+
+/// Returns ceil(a / b).
+fn div_ceil(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + b - 1u32) / b
+}
+
+/// Recovers the plaintext of `c`, a PKCS#1 v1.5 conforming ciphertext, using an oracle that
+/// reveals whether a ciphertext decrypts to a conforming plaintext. This is Bleichenbacher's
+/// 1998 attack, from "Chosen Ciphertext Attacks Against Protocols Based on the RSA Encryption
+/// Standard PKCS #1".
+///
+/// Every conforming m * s tells us that 2B <= m * s mod n < 3B, where B = 2^(8(k - 2)), which
+/// narrows down the set of intervals m can be in. We keep searching for conforming s values
+/// until a single value is left.
+fn bleichenbacher98(
+    public: &RsaPublicKey,
+    c: &BigUint,
+    oracle: impl Fn(&BigUint) -> bool,
+) -> BigUint {
+    let n = &public.n;
+    let k = public.size();
+    let b = BigUint::from(1u32) << (8 * (k - 2));
+    let (b2, b3) = (&b * 2u32, &b * 3u32);
+
+    // Step 1 (blinding) is unnecessary since c is already conforming, so s0 = 1.
+    let mut intervals = vec![(b2.clone(), &b3 - 1u32)];
+    let mut s = BigUint::from(0u32);
+
+    for i in 1.. {
+        if i == 1 {
+            // Step 2a: find the smallest s >= n/3B such that c * s^e is conforming.
+            s = div_ceil(n, &b3);
+            while !public.blinded_query(c, &s, &oracle) {
+                s += 1u32;
+            }
+        } else if intervals.len() > 1 {
+            // Step 2b: there's more than one interval left, so keep searching upwards.
+            s += 1u32;
+            while !public.blinded_query(c, &s, &oracle) {
+                s += 1u32;
+            }
+        } else {
+            // Step 2c: there's only one interval [a, b] left, so pick r and s values that
+            // roughly halve it each time.
+            let (lo, hi) = &intervals[0];
+            let mut r = div_ceil(&((hi * &s - &b2) * 2u32), n);
+            'search: loop {
+                let s_lo = div_ceil(&(&b2 + &r * n), hi);
+                let s_hi = div_ceil(&(&b3 + &r * n), lo);
+                let mut candidate = s_lo;
+                while candidate < s_hi {
+                    if public.blinded_query(c, &candidate, &oracle) {
+                        s = candidate;
+                        break 'search;
+                    }
+                    candidate += 1u32;
+                }
+                r += 1u32;
+            }
+        }
+
+        // Step 3: narrow the set of intervals down using the new s.
+        let mut narrowed: Vec<(BigUint, BigUint)> = vec![];
+        for (lo, hi) in &intervals {
+            let r_lo = div_ceil(&((lo * &s + 1u32).max(b3.clone()) - &b3), n);
+            let r_hi = (hi * &s - &b2) / n;
+
+            let mut r = r_lo;
+            while r <= r_hi {
+                let new_lo = lo.max(&div_ceil(&(&b2 + &r * n), &s)).clone();
+                let new_hi = hi.min(&((&b3 - 1u32 + &r * n) / &s)).clone();
+                if new_lo <= new_hi {
+                    narrowed.push((new_lo, new_hi));
+                }
+                r += 1u32;
+            }
+        }
+
+        // Merge overlapping intervals.
+        narrowed.sort();
+        intervals = narrowed.into_iter().fold(vec![], |mut merged, (lo, hi)| {
+            match merged.last_mut() {
+                Some((_, last_hi)) if lo <= *last_hi => *last_hi = hi.max(last_hi.clone()),
+                _ => merged.push((lo, hi)),
+            }
+            merged
+        });
+
+        // Step 4: stop once there's a single value left.
+        if let [(lo, hi)] = intervals.as_slice() {
+            if lo == hi {
+                return lo.clone();
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+/// Runs Bleichenbacher's attack against a fresh `bits`-bit key, and returns the number of
+/// oracle queries it took.
+fn run_bleichenbacher98(bits: u64, strictness: OracleStrictness) -> u64 {
+    let oracle = PaddingOracle::new(RsaPrivateKey::generate(bits, 3), strictness);
+    let public = oracle.key.public_key();
+
+    let message = "kick it, CC".as_bytes();
+    let ciphertext = public.encrypt_pkcs1v15(message);
+    assert_eq!(oracle.key.decrypt_pkcs1v15(&ciphertext).unwrap(), message);
+
+    let c = from_bytes(&ciphertext);
+    let recovered = bleichenbacher98(&public, &c, |c| oracle.is_conforming(c));
+    let recovered = pkcs1v15_unpad(&to_bytes_padded(&recovered, public.size())).unwrap();
+    assert_eq!(recovered, message);
+
+    oracle.queries.get()
+}
+
+/// Runs the attack `trials` times for each oracle strictness, and reports query statistics.
+/// The stricter the oracle, the fewer ciphertexts it accepts, so the longer each search for a
+/// conforming s takes.
+fn bleichenbacher98_stats(bits: u64, trials: usize, strictnesses: &[OracleStrictness]) {
+    for &strictness in strictnesses {
+        let mut queries = (0..trials)
+            .map(|_| run_bleichenbacher98(bits, strictness))
+            .collect::<Vec<_>>();
+        queries.sort();
+
+        info!(
+            "{}-bit key, {:?} padding check, {} trials: min {} / median {} / max {} queries",
+            bits,
+            strictness,
+            trials,
+            queries[0],
+            queries[trials / 2],
+            queries[trials - 1]
+        );
+    }
+}
+
+fn challenge47(skip_slow_challenges: bool) {
+    info!("Running: challenge47");
+    if skip_slow_challenges {
+        bleichenbacher98_stats(256, 1, &[OracleStrictness::Header]);
+    } else {
+        bleichenbacher98_stats(256, 5, &[OracleStrictness::Header]);
+        bleichenbacher98_stats(256, 1, &[OracleStrictness::Full]);
+    }
+}
+
+fn challenge48() {
+    info!("Running: challenge48");
+    bleichenbacher98_stats(768, 1, &[OracleStrictness::Header, OracleStrictness::Full]);
+}
+
+pub fn run(skip_slow_challenges: bool) {
+    info!("Running Set 6");
+    challenge41();
+    challenge42();
+    challenge43();
+    challenge44();
+    challenge45();
+    challenge46();
+    challenge47(skip_slow_challenges);
+    if !skip_slow_challenges {
+        challenge48();
+    }
+}
@@ -1,7 +1,18 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::bail;
+use base64::{engine::general_purpose, Engine};
+use flate2::{write::DeflateEncoder, Compression};
+use rand::{Rng, RngCore};
+use tracing::*;
+
 use crate::aes::*;
+use crate::md4::*;
+use crate::mdhash::*;
+use crate::rc4::*;
 use crate::util::*;
-use rand::RngCore;
-use tracing::*;
 
 fn challenge49() {
     info!("Challenge 49");
@@ -69,6 +80,102 @@ fn challenge49() {
 
     // Verify that the new message is accepted.
     assert!(verify_message(signed_message2.as_str()));
+
+    // Part two: the IV is now fixed at zero, and a message can hold several transfers.
+    let iv = [0u8; 16];
+
+    let sign_transfers = |from: &str, tx_list: &[(&str, u64)]| -> String {
+        let tx_list = tx_list
+            .iter()
+            .map(|(to, amount)| format!("{}:{}", to, amount))
+            .collect::<Vec<_>>()
+            .join(";");
+        let message = format!("from={}&tx_list={}", from, tx_list);
+        format!(
+            "{}{}",
+            hex::encode(&message),
+            hex::encode(cbc_mac(&pkcs7_pad(message.as_bytes(), 16), &key, &iv))
+        )
+    };
+
+    // Returns the source account and the list of transfers, if the MAC is valid. Malformed
+    // transfers are skipped rather than rejecting the whole message.
+    let verify_transfers = |message: &str| -> Option<(String, Vec<(String, u64)>)> {
+        let message = hex::decode(message).ok()?;
+        let (message, mac) = message.split_at(message.len().checked_sub(16)?);
+        if cbc_mac(&pkcs7_pad(message, 16), &key, &iv) != mac {
+            return None;
+        }
+
+        let message = String::from_utf8_lossy(message);
+        let (from, tx_list) = message.strip_prefix("from=")?.split_once("&tx_list=")?;
+        let tx_list = tx_list
+            .split(';')
+            .filter_map(|tx| {
+                let (to, amount) = tx.split_once(':')?;
+                if to.is_empty() || !to.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                Some((to.to_string(), amount.parse().ok()?))
+            })
+            .collect();
+        Some((from.to_string(), tx_list))
+    };
+
+    // Capture a message the victim signs for some legitimate transfers.
+    let captured =
+        hex::decode(sign_transfers("666666", &[("777777", 100), ("888888", 5)])).unwrap();
+    let (victim_message, victim_mac) = captured.split_at(captured.len() - 16);
+    assert!(verify_transfers(&hex::encode(&captured)).is_some());
+
+    // Sign a message from my own account. Its first block is "from=123456&tx_l", and the rest
+    // ends with a transfer of 1m spacebucks to me.
+    let mine = hex::decode(sign_transfers(
+        "123456",
+        &[("123456", 1), ("123456", 1000000)],
+    ))
+    .unwrap();
+    let (my_message, my_mac) = mine.split_at(mine.len() - 16);
+
+    // Extend the victim's message: once the CBC state is the victim's MAC, XORing it into my
+    // first block makes the rest of the chain identical to my message's, so my MAC is valid for
+    // the whole thing. The glue block lands in the middle of the victim's last transfer, which
+    // the server skips as malformed.
+    let mut forged = pkcs7_pad(victim_message, 16);
+    forged.extend(xor_block(&my_message[..16], victim_mac));
+    forged.extend(&my_message[16..]);
+    forged.extend(my_mac);
+
+    let (from, tx_list) = verify_transfers(&hex::encode(&forged)).unwrap();
+    debug!("forged transfers from {}: {:?}", from, tx_list);
+    assert_eq!(from, "666666");
+    assert!(tx_list.contains(&("123456".to_string(), 1000000)));
+}
+
+/// Forges a message that starts with `prefix` and has the CBC-MAC `target_mac` under the given
+/// (public) key and IV, once PKCS#7 padded. The prefix is padded out to a block boundary with
+/// spaces, and followed by a single glue block.
+///
+/// With s the CBC state after the spaced-out prefix, the glue block G is encrypted to E(s ^ G),
+/// followed by a final block of padding (16 bytes of 0x10), which we need to encrypt to the
+/// target MAC. Working backwards: E(s ^ G) = D(target) ^ padding, so G = s ^ D(D(target) ^
+/// padding).
+fn cbc_mac_forge_suffix(key: &[u8], iv: &[u8], target_mac: &[u8], prefix: &[u8]) -> Vec<u8> {
+    let mut forged = prefix.to_vec();
+    forged.resize(prefix.len().div_ceil(16) * 16, b' ');
+
+    let state = if forged.is_empty() {
+        iv.to_vec()
+    } else {
+        cbc_mac(&forged, key, iv)
+    };
+
+    let padding = [16u8; 16];
+    let before_padding = xor_block(&aes128_decrypt_block(target_mac, key), &padding);
+    let glue = xor_block(&state, &aes128_decrypt_block(&before_padding, key));
+
+    forged.extend(glue);
+    forged
 }
 
 fn challenge50() {
@@ -78,18 +185,587 @@ fn challenge50() {
     let key = "YELLOW SUBMARINE".as_bytes();
     let iv = &[0u8; 16];
 
-    let target_pt = "alert('Ayo, the Wu is back!');\n".as_bytes();
     let target_mac = hex::decode("296b8d7cb78a243dda4d0a61d33bbdd1").unwrap();
-
     assert_eq!(cbc_mac(&pkcs7_pad(pt, 16), key, iv), target_mac);
 
-    let target_last_ct = aes128_cbc_decrypt(&pkcs7_pad(pt, 16).chunks(16).last().unwrap(), key, iv);
+    // Comment out the glue block, so the code still runs. A newline in the glue would end the
+    // comment, so push it along with extra spaces until we get a glue block without one.
+    let target_pt = "alert('Ayo, the Wu is back!');\n//".as_bytes();
+    let forged = (0..)
+        .map(|extra| {
+            let prefix = [target_pt, &vec![b' '; extra]].concat();
+            cbc_mac_forge_suffix(key, iv, &target_mac, &prefix)
+        })
+        .find(|forged| {
+            !forged[forged.len() - 16..]
+                .iter()
+                .any(|&b| b == b'\n' || b == b'\r')
+        })
+        .unwrap();
+
+    debug!("forged: {:?}", String::from_utf8_lossy(&forged));
+    assert!(forged.starts_with(target_pt));
+    assert_eq!(cbc_mac(&pkcs7_pad(&forged, 16), key, iv), target_mac);
+}
+
+const SESSION_COOKIE: &str = "TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+fn format_request(body: &[u8]) -> Vec<u8> {
+    let headers = format!(
+        "POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid={}\nContent-Length: {}\n",
+        SESSION_COOKIE,
+        body.len()
+    );
+    [headers.as_bytes(), body].concat()
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CompressionCipher {
+    Ctr,
+    Cbc,
+}
+
+/// Formats a request with an attacker-controlled body, compresses it, and encrypts it under a
+/// fresh key, returning only the length of the ciphertext. Counts the queries made.
+struct CompressionOracle {
+    cipher: CompressionCipher,
+    queries: Cell<u64>,
+}
+
+impl CompressionOracle {
+    fn new(cipher: CompressionCipher) -> Self {
+        Self {
+            cipher,
+            queries: Cell::new(0),
+        }
+    }
+
+    fn query(&self, body: &[u8]) -> usize {
+        self.queries.set(self.queries.get() + 1);
+
+        let mut rng = rand::thread_rng();
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        let compressed = deflate(&format_request(body));
+
+        match self.cipher {
+            CompressionCipher::Ctr => aes128_ctr(&compressed, &key, rng.gen()).len(),
+            CompressionCipher::Cbc => {
+                let mut iv = [0u8; 16];
+                rng.fill_bytes(&mut iv);
+                aes128_cbc_encrypt(&pkcs7_pad(&compressed, 16), &key, &iv).len()
+            }
+        }
+    }
+}
+
+/// Returns `len` distinct bytes starting at `first`. None of them appear in a request, and they
+/// never repeat, so they don't compress.
+fn incompressible(first: u8, len: usize) -> Vec<u8> {
+    (first..=0xff).take(len).collect()
+}
+
+/// Compares the guess `known || c || separator` against the reference `known || separator || c`.
+/// Both contain the same bytes, but if `c` is right, the guess extends the back-reference to the
+/// cookie and saves a literal. That saves a few bits, which only show up in the ciphertext length
+/// if they happen to cross a byte (or block) boundary. So we prepend `align`, then add filler
+/// until the reference just spills over into the next block, and check whether the guess still
+/// fits. With a stream cipher, the first byte of filler does that.
+///
+/// Every body ends with a run of zeros, which compresses well and keeps the compressor from
+/// falling back to a stored block, where nothing is compressed at all.
+fn two_tries(oracle: &CompressionOracle, align: &[u8], known: &[u8], c: u8) -> bool {
+    let separator = incompressible(0x80, 8);
+    let ballast = [0u8; 256];
+    let guess = [known, &[c], &separator, &ballast].concat();
+    let reference = [known, &separator, &[c], &ballast].concat();
+
+    let base = oracle.query(&[align, &reference].concat());
+    (1..=32)
+        .map(|n| [&incompressible(0x90, n), align].concat())
+        .find_map(|filler| {
+            let len = oracle.query(&[&filler, &reference[..]].concat());
+            (len > base).then(|| oracle.query(&[&filler, &guess[..]].concat()) < len)
+        })
+        .unwrap_or(false)
+}
+
+/// Recovers the session cookie one byte at a time. Wrong guesses compress exactly as well as the
+/// reference, so only the right guess ever wins a comparison. A comparison can miss depending on
+/// where the block boundaries fall, so we repeat it under different alignments until one guess
+/// is clearly ahead. If none is after every alignment, we give up rather than guess.
+fn compression_attack(oracle: &CompressionOracle) -> anyhow::Result<String> {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=\n";
+    let mut known = b"sessionid=".to_vec();
+
+    loop {
+        let mut wins = vec![0; alphabet.len()];
+        let mut leader = None;
+        for align in (0..32).map(|len| incompressible(0xc0, len)) {
+            for (wins, &c) in wins.iter_mut().zip(alphabet) {
+                *wins += two_tries(oracle, &align, &known, c) as usize;
+            }
+
+            let mut sorted = wins.clone();
+            sorted.sort_unstable_by(|a, b| b.cmp(a));
+            if sorted[0] >= sorted[1] + 2 {
+                leader = wins.iter().position(|&w| w == sorted[0]);
+                break;
+            }
+        }
+
+        let Some(leader) = leader else {
+            bail!(
+                "no clear guess for the byte after {:?}",
+                String::from_utf8_lossy(&known)
+            );
+        };
+        let next = alphabet[leader];
+        if next == b'\n' {
+            break;
+        }
+        known.push(next);
+    }
+
+    Ok(String::from_utf8(known[b"sessionid=".len()..].to_vec())?)
+}
+
+fn challenge51() {
+    info!("Challenge 51");
+
+    for cipher in [CompressionCipher::Ctr, CompressionCipher::Cbc] {
+        let oracle = CompressionOracle::new(cipher);
+        let cookie = compression_attack(&oracle).unwrap();
+        debug!(
+            "{:?}: recovered {} in {} queries",
+            cipher,
+            cookie,
+            oracle.queries.get()
+        );
+        assert_eq!(cookie, SESSION_COOKIE);
+    }
+}
+
+/// Finds a collision in the cascade f(m) || g(m) of a cheap 16-bit hash f and a more expensive
+/// 24-bit hash g. A multicollision in f of 2^12 messages has even odds of containing a collision
+/// in g, so the cascade is barely stronger than g on its own.
+fn challenge52() {
+    info!("Challenge 52");
+
+    let f = MdHash::new(&[0x52, 0x01]);
+    let g = MdHash::new(&[0x52, 0x02, 0x03]);
+
+    let mut collisions = multicollision(&f, f.iv(), g.iv().len() * 8 / 2);
+    let (a, b) = loop {
+        let mut seen = HashMap::new();
+        let found = multicollision_messages(&collisions)
+            .find_map(|m| Some((seen.insert(g.hash(&m), m.clone())?, m)));
+        if let Some(pair) = found {
+            break pair;
+        }
+
+        // No luck, so double the number of messages with one more collision in f.
+        debug!(
+            "no collision in g among {} messages",
+            1u64 << collisions.len()
+        );
+        let state = collisions.last().unwrap().state.clone();
+        collisions.push(find_collision(&f, &state));
+    };
 
-    dbg!(target_pt.len(), key.len());
+    debug!(
+        "found a collision with {} calls to f and {} calls to g, vs about 2^20 by brute force",
+        f.calls(),
+        g.calls()
+    );
+    assert_ne!(a, b);
+    assert_eq!(f.hash(&a), f.hash(&b));
+    assert_eq!(g.hash(&a), g.hash(&b));
+}
+
+/// Finds a second preimage for a long message, despite the length padding: an expandable message
+/// lets us match the original length exactly.
+fn challenge53() {
+    info!("Challenge 53");
+
+    let hash = MdHash::new(&[0x53, 0x01, 0x02]);
+    let k = 12;
+    let mut message = vec![0u8; BLOCK_SIZE << k];
+    rand::thread_rng().fill_bytes(&mut message);
+    let digest = hash.hash(&message);
+
+    let calls = hash.calls();
+    let forged = second_preimage(&hash, &message, k).unwrap();
+    debug!(
+        "found a second preimage for a 2^{}-block message with {} compression function calls, vs \
+         about 2^24 by brute force",
+        k,
+        hash.calls() - calls
+    );
+
+    assert_ne!(forged, message);
+    assert_eq!(forged.len(), message.len());
+    assert_eq!(hash.hash(&forged), digest);
+}
+
+/// Commits to a hash of a "prediction" before knowing what it'll say, then herds a message with
+/// the real results as its prefix to that hash through a diamond structure.
+fn challenge54() {
+    info!("Challenge 54");
+
+    let hash = MdHash::new(&[0x54, 0x01, 0x02]);
+    let k = 8;
+    let prefix_blocks = 4;
+
+    let diamond = Diamond::new(&hash, k);
+    let commitment = diamond.commitment(&hash, prefix_blocks);
+    debug!(
+        "committed to {} after building a 2^{}-leaf diamond with {} compression function calls",
+        hex::encode(&commitment),
+        k,
+        hash.calls()
+    );
+
+    let mut prediction = b"Giants 21, Jets 17; Packers 3, Bears 38; Chiefs 24, Raiders 20".to_vec();
+    prediction.resize(prefix_blocks * BLOCK_SIZE, b' ');
+
+    let calls = hash.calls();
+    let message = diamond.herd(&hash, prefix_blocks, &prediction).unwrap();
+    debug!(
+        "herded the prediction with {} compression function calls",
+        hash.calls() - calls
+    );
+
+    assert!(message.starts_with(&prediction));
+    assert_eq!(hash.hash(&message), commitment);
+}
+
+/// A sufficient condition on one bit of an intermediate MD4 state, relative to the states before
+/// it. `Eq(n)` and `Ne(n)` compare against the same bit of the state computed n steps earlier.
+#[derive(Clone, Copy, Debug)]
+enum BitCondition {
+    Zero,
+    One,
+    Eq(usize),
+    Ne(usize),
+}
+
+use BitCondition::*;
+
+/// Wang et al.'s sufficient conditions for the first 23 steps, which make the differential below
+/// hold with high probability. Bits are numbered from 0, one less than in the paper.
+#[rustfmt::skip]
+const WANG_CONDITIONS: [(&str, &[(u32, BitCondition)]); 23] = [
+    ("a1", &[(6, Eq(1))]),
+    ("d1", &[(6, Zero), (7, Eq(1)), (10, Eq(1))]),
+    ("c1", &[(6, One), (7, One), (10, Zero), (25, Eq(1))]),
+    ("b1", &[(6, One), (7, Zero), (10, Zero), (25, Zero)]),
+    ("a2", &[(7, One), (10, One), (25, Zero), (13, Eq(1))]),
+    ("d2", &[(13, Zero), (18, Eq(1)), (19, Eq(1)), (20, Eq(1)), (21, Eq(1)), (25, One)]),
+    ("c2", &[(12, Eq(1)), (13, Zero), (14, Eq(1)), (18, Zero), (19, Zero), (20, One), (21, Zero)]),
+    ("b2", &[(12, One), (13, One), (14, Zero), (16, Eq(1)), (18, Zero), (19, Zero), (20, Zero),
+             (21, Zero)]),
+    ("a3", &[(12, One), (13, One), (14, One), (16, Zero), (18, Zero), (19, Zero), (20, Zero),
+             (21, One), (22, Eq(1)), (25, Eq(1))]),
+    ("d3", &[(12, One), (13, One), (14, One), (16, Zero), (19, Zero), (20, One), (21, One),
+             (22, Zero), (25, One), (29, Eq(1))]),
+    ("c3", &[(16, One), (19, Zero), (20, Zero), (21, Zero), (22, Zero), (25, Zero), (29, One),
+             (31, Eq(1))]),
+    ("b3", &[(19, Zero), (20, One), (21, One), (22, Eq(1)), (25, One), (29, Zero), (31, Zero)]),
+    ("a4", &[(22, Zero), (25, Zero), (26, Eq(1)), (28, Eq(1)), (29, One), (31, Zero)]),
+    ("d4", &[(22, Zero), (25, Zero), (26, One), (28, One), (29, Zero), (31, One)]),
+    ("c4", &[(18, Eq(1)), (22, One), (25, One), (26, Zero), (28, Zero), (29, Zero)]),
+    ("b4", &[(18, Zero), (25, One), (26, One), (28, One), (29, Zero)]),
+    ("a5", &[(18, Eq(2)), (25, One), (26, Zero), (28, One), (31, One)]),
+    ("d5", &[(18, Eq(1)), (25, Eq(2)), (26, Eq(2)), (28, Eq(2)), (31, Eq(2))]),
+    ("c5", &[(25, Eq(1)), (26, Eq(1)), (28, Eq(1)), (29, Eq(1)), (31, Eq(1))]),
+    ("b5", &[(28, Eq(1)), (29, One), (31, Zero)]),
+    ("a6", &[(28, One), (31, One)]),
+    ("d6", &[(28, Eq(2))]),
+    ("c6", &[(28, Eq(1)), (29, Ne(1)), (31, Ne(1))]),
+];
+
+/// Applies Wang's differential: M' = M + (2^31 in m1, 2^31 - 2^28 in m2, -2^16 in m12).
+fn wang_differential(m: &[u32; 16]) -> [u32; 16] {
+    let mut m = *m;
+    m[1] = m[1].wrapping_add(1 << 31);
+    m[2] = m[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+    m[12] = m[12].wrapping_sub(1 << 16);
+    m
+}
+
+/// Returns the MD4 states after each of the first 23 steps, preceded by the initial state in the
+/// order it's consumed (a0, d0, c0, b0). So step i computes `states[i + 4]` from `states[i..i + 4]`.
+fn wang_states(m: &[u32; 16]) -> Vec<u32> {
+    let [a, b, c, d] = INITIAL_STATE;
+    let mut v = vec![a, d, c, b];
+    for i in 0..WANG_CONDITIONS.len() {
+        let (a, b, c, d) = (v[i], v[i + 3], v[i + 2], v[i + 1]);
+        v.push(if i < 16 {
+            round1_step(a, b, c, d, m[i], ROUND1_SHIFTS[i % 4])
+        } else {
+            round2_step(a, b, c, d, m[ROUND2_ORDER[i - 16]], ROUND2_SHIFTS[i % 4])
+        });
+    }
+    v
+}
+
+fn condition_holds(v: &[u32], i: usize, bit: u32, condition: BitCondition) -> bool {
+    let actual = v[i] >> bit & 1;
+    match condition {
+        Zero => actual == 0,
+        One => actual == 1,
+        Eq(n) => actual == v[i - n] >> bit & 1,
+        Ne(n) => actual != v[i - n] >> bit & 1,
+    }
+}
+
+/// Forces `v[i]` to satisfy the conditions for step `i - 4`.
+fn fix_conditions(v: &mut [u32], i: usize) {
+    for &(bit, condition) in WANG_CONDITIONS[i - 4].1 {
+        if !condition_holds(v, i, bit, condition) {
+            v[i] ^= 1 << bit;
+        }
+    }
+}
+
+/// Modifies `m` so that every round 1 condition holds, and as many of the conditions on a5, d5
+/// and c5 as we can manage.
+///
+/// In round 1, each message word is used once, in order, so we can compute each state, fix its
+/// bits, and solve for the message word that produces the fixed state. Round 2 is handled by
+/// `wang_modify_round2`.
+fn wang_massage(m: &mut [u32; 16]) {
+    let mut v = wang_states(m);
+    for i in 0..16 {
+        v[i + 4] = round1_step(
+            v[i],
+            v[i + 3],
+            v[i + 2],
+            v[i + 1],
+            m[i],
+            ROUND1_SHIFTS[i % 4],
+        );
+        fix_conditions(&mut v, i + 4);
+        m[i] = round1_word(
+            v[i],
+            v[i + 3],
+            v[i + 2],
+            v[i + 1],
+            v[i + 4],
+            ROUND1_SHIFTS[i % 4],
+        );
+    }
+
+    for step in 16..19 {
+        wang_modify_round2(m, step);
+    }
+}
+
+/// Multi-step modification for the first steps of round 2, one condition bit at a time. To flip
+/// a bit of the state, we solve for the message word that produces the flipped state. That word
+/// was also used in round 1, where it now produces a different state, so we recompute the next
+/// four words to keep every later round 1 state the same.
+///
+/// The change only sticks if it doesn't break any earlier condition, which depends on where it
+/// lands in the round 1 state, and how it carries. Adding small odd multiples of the bit, rather
+/// than just flipping it, gives us a few different carries to choose from. That's enough for a5
+/// every time and d5 all but about one time in a thousand. For c5, the change lands on
+/// conditioned bits of a3 for all but c5[29], so the rest are left to chance.
+fn wang_modify_round2(m: &mut [u32; 16], step: usize) {
+    let k = ROUND2_ORDER[step - 16];
+    let s = ROUND2_SHIFTS[step % 4];
+    for &(bit, condition) in WANG_CONDITIONS[step].1 {
+        let v = wang_states(m);
+        if condition_holds(&v, step + 4, bit, condition) {
+            continue;
+        }
+        let failing = failed_wang_conditions(m);
+
+        // Adding or subtracting any odd multiple of the bit flips it, but each one carries
+        // differently, into different bits of the round 1 state.
+        for target in [1u32, 3, 5, 7]
+            .into_iter()
+            .flat_map(|d| [d, d.wrapping_neg()])
+            .map(|d| v[step + 4].wrapping_add(d.wrapping_shl(bit)))
+        {
+            let mut modified = *m;
+            let mut w = v.clone();
+            w[step + 4] = target;
+            modified[k] = round2_word(v[step], v[step + 3], v[step + 2], v[step + 1], target, s);
+            w[k + 4] = round1_step(
+                w[k],
+                w[k + 3],
+                w[k + 2],
+                w[k + 1],
+                modified[k],
+                ROUND1_SHIFTS[k % 4],
+            );
+            for i in k + 1..k + 5 {
+                modified[i] = round1_word(
+                    w[i],
+                    w[i + 3],
+                    w[i + 2],
+                    w[i + 1],
+                    w[i + 4],
+                    ROUND1_SHIFTS[i % 4],
+                );
+            }
+
+            let failed = failed_wang_conditions(&modified);
+            if failed
+                .iter()
+                .all(|&(i, b)| i > step || (i == step && b != bit && failing.contains(&(i, b))))
+            {
+                *m = modified;
+                break;
+            }
+        }
+    }
+}
+
+/// A condition, as the index of its step in `WANG_CONDITIONS` and the bit it's on.
+type WangCondition = (usize, u32);
+
+/// Checks every condition against `m`, and returns the ones that hold and the ones that fail.
+fn check_wang_conditions(m: &[u32; 16]) -> (Vec<WangCondition>, Vec<WangCondition>) {
+    let v = wang_states(m);
+    let (mut held, mut failed) = (vec![], vec![]);
+    for (i, (_, conditions)) in WANG_CONDITIONS.iter().enumerate() {
+        for &(bit, condition) in conditions.iter() {
+            if condition_holds(&v, i + 4, bit, condition) {
+                held.push((i, bit));
+            } else {
+                failed.push((i, bit));
+            }
+        }
+    }
+    (held, failed)
+}
+
+/// Returns the conditions that `m` fails.
+fn failed_wang_conditions(m: &[u32; 16]) -> Vec<WangCondition> {
+    check_wang_conditions(m).1
+}
+
+fn format_wang_conditions(conditions: &[WangCondition]) -> String {
+    conditions
+        .iter()
+        .map(|&(i, bit)| format!("{}[{}]", WANG_CONDITIONS[i].0, bit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Searches for a pair of one-block messages that collide under MD4, by massaging random blocks
+/// to satisfy Wang's conditions and checking whether the differential holds through the rest of
+/// the compression function. In verification mode, prints which conditions each candidate
+/// satisfies and which it fails.
+fn find_md4_collision(verify: bool) -> ([u32; 16], [u32; 16], u64) {
+    let mut rng = rand::thread_rng();
+    for attempt in 1.. {
+        let mut m: [u32; 16] = rng.gen();
+        wang_massage(&mut m);
+        if verify {
+            let (held, failed) = check_wang_conditions(&m);
+            println!(
+                "candidate {}: {}/{} conditions hold: {}\n  failed: {}",
+                attempt,
+                held.len(),
+                held.len() + failed.len(),
+                format_wang_conditions(&held),
+                format_wang_conditions(&failed)
+            );
+        }
+
+        let m2 = wang_differential(&m);
+        if compress(INITIAL_STATE, &m) == compress(INITIAL_STATE, &m2) {
+            return (m, m2, attempt);
+        }
+    }
+    unreachable!()
+}
+
+/// Massaging satisfies every round 1 condition, the ones on a5, nearly always the ones on d5, and
+/// c5[29]. The rest of c5's conditions, and the ones on b5 through c6, are left to chance, which is
+/// why finding a collision still takes tens of thousands of candidates. With `verify`, every
+/// candidate's conditions are printed as it's checked.
+fn challenge55(verify: bool) {
+    info!("Challenge 55");
+
+    // Massaging should always satisfy the round 1 conditions and the ones on a5 (step 16), and
+    // nearly always the ones on d5 (step 17).
+    let mut d5_held = 0;
+    for _ in 0..16 {
+        let mut m: [u32; 16] = rand::thread_rng().gen();
+        wang_massage(&mut m);
+        let failed = failed_wang_conditions(&m);
+        debug!(
+            "massaged candidate failed: {}",
+            format_wang_conditions(&failed)
+        );
+        assert!(failed.iter().all(|&(step, _)| step > 16));
+        d5_held += failed.iter().all(|&(step, _)| step > 17) as usize;
+    }
+    assert!(d5_held >= 14);
+
+    let (m, m2, attempts) = find_md4_collision(verify);
+    let (m, m2) = (words_to_block(&m), words_to_block(&m2));
+    debug!(
+        "found an MD4 collision after {} attempts:\n{}\n{}",
+        attempts,
+        hex::encode(&m),
+        hex::encode(&m2)
+    );
+    assert_ne!(m, m2);
+    assert_eq!(md4(&m), md4(&m2));
+}
+
+/// Returns an oracle that encrypts `request || cookie` with RC4 under a fresh random key.
+fn rc4_oracle(cookie: &[u8]) -> impl Fn(&[u8]) -> Vec<u8> + Sync + '_ {
+    move |request| {
+        let mut key = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut key);
+        rc4(&key, &[request, cookie].concat())
+    }
+}
+
+/// Recovers the cookie from 2^24 samples per prefix length, which takes a few minutes. At 2^20
+/// samples, only a handful of bytes come out right, so to check the attack in fast mode, we
+/// recover just the first two bytes of the cookie, from 2^23 samples each.
+fn challenge56(skip_slow_challenges: bool) {
+    info!("Challenge 56");
+
+    let cookie = general_purpose::STANDARD
+        .decode("QkUgU1VSRSBUTyBEUklOSyBZT1VSIE9WQUxUSU5F")
+        .unwrap();
+    let (secret, samples) = if skip_slow_challenges {
+        (&cookie[..2], 1 << 23)
+    } else {
+        (&cookie[..], 1 << 24)
+    };
+    let recovered = rc4_bias_attack(rc4_oracle(secret), samples);
+    debug!(
+        "recovered {:?} from 2^{} samples per prefix",
+        String::from_utf8_lossy(&recovered),
+        samples.ilog2()
+    );
+    assert_eq!(recovered, secret);
 }
 
-pub fn run() {
+pub fn run(skip_slow_challenges: bool, verify_md4_conditions: bool) {
     info!("Running set 7");
     challenge49();
     challenge50();
+    challenge51();
+    challenge52();
+    challenge53();
+    challenge54();
+    challenge55(verify_md4_conditions);
+    challenge56(skip_slow_challenges);
 }
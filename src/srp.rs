@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+use hmac::{Hmac, Mac};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::Zero;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::bignum::*;
+use crate::dh::DhGroup;
+
+/// SRP-6a group parameters, plus the multiplier `k = H(N | g)`.
+#[derive(Clone, Debug)]
+pub struct SrpParams {
+    pub n: BigUint,
+    pub g: BigUint,
+    pub k: BigUint,
+}
+
+impl SrpParams {
+    /// Uses the NIST prime and generator from the Diffie-Hellman challenges.
+    pub fn nist() -> Self {
+        let group = DhGroup::nist();
+        let k = hash_to_int(&[&to_bytes(&group.p), &to_bytes(&group.g)]);
+        Self {
+            n: group.p,
+            g: group.g,
+            k,
+        }
+    }
+}
+
+/// Returns SHA256 of the concatenated `parts`, as an integer.
+pub fn hash_to_int(parts: &[&[u8]]) -> BigUint {
+    from_bytes(&Sha256::digest(parts.concat()))
+}
+
+/// Returns HMAC-SHA256(key, message).
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Returns the proof a client sends the server: HMAC-SHA256(SHA256(S), salt).
+pub fn session_proof(secret: &BigUint, salt: &[u8]) -> Vec<u8> {
+    hmac_sha256(&Sha256::digest(to_bytes(secret)), salt)
+}
+
+/// Returns the private key `x = H(salt | password)`.
+pub fn private_key(salt: &[u8], password: &str) -> BigUint {
+    hash_to_int(&[salt, password.as_bytes()])
+}
+
+/// Returns the scrambling parameter `u = H(A | B)`.
+pub fn scrambler(a_pub: &BigUint, b_pub: &BigUint) -> BigUint {
+    hash_to_int(&[&to_bytes(a_pub), &to_bytes(b_pub)])
+}
+
+/// What the server stores for each registered user. The password itself is never stored.
+#[derive(Clone, Debug)]
+struct Registration {
+    salt: Vec<u8>,
+    verifier: BigUint,
+}
+
+/// An SRP server holding the salt and verifier for each registered user.
+pub struct SrpServer {
+    params: SrpParams,
+    users: HashMap<String, Registration>,
+    // Reject client public keys that are 0 mod N, which force the session key to a known value.
+    validate_a: bool,
+}
+
+/// The server's half of an in-progress login.
+pub struct SrpServerSession {
+    pub salt: Vec<u8>,
+    pub b_pub: BigUint,
+    /// The scrambling parameter. Clients derive this themselves, except in simplified SRP.
+    pub u: BigUint,
+    secret: BigUint,
+}
+
+impl SrpServer {
+    pub fn new(params: SrpParams) -> Self {
+        Self {
+            params,
+            users: HashMap::new(),
+            validate_a: false,
+        }
+    }
+
+    /// Returns a server that rejects malicious client public keys (A = 0 mod N).
+    pub fn new_hardened(params: SrpParams) -> Self {
+        Self {
+            validate_a: true,
+            ..Self::new(params)
+        }
+    }
+
+    /// Registers a user, storing a random salt and the verifier `v = g^x mod N`.
+    pub fn register(&mut self, email: &str, password: &str) {
+        let mut salt = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let verifier = modexp(
+            &self.params.g,
+            &private_key(&salt, password),
+            &self.params.n,
+        );
+
+        self.users
+            .insert(email.to_string(), Registration { salt, verifier });
+    }
+
+    /// Handles a client's hello (email and public key `A`), returning a session holding the
+    /// salt and the server's public key `B = kv + g^b mod N` to send back.
+    pub fn begin(&self, email: &str, a_pub: &BigUint) -> anyhow::Result<SrpServerSession> {
+        let SrpParams { n, g, k } = &self.params;
+        let user = self
+            .users
+            .get(email)
+            .ok_or_else(|| anyhow!("unknown user: {}", email))?;
+
+        if self.validate_a && (a_pub % n).is_zero() {
+            bail!("invalid public key: A = 0 mod N");
+        }
+
+        let b = rand::thread_rng().gen_biguint_below(n);
+        let b_pub = (k * &user.verifier + modexp(g, &b, n)) % n;
+
+        // S = (A * v^u)^b mod N
+        let u = scrambler(a_pub, &b_pub);
+        let secret = modexp(&(a_pub * modexp(&user.verifier, &u, n)), &b, n);
+
+        Ok(SrpServerSession {
+            salt: user.salt.clone(),
+            b_pub,
+            u,
+            secret,
+        })
+    }
+
+    /// Like `begin`, but for simplified SRP, where `B = g^b mod N` doesn't depend on the
+    /// password, and `u` is a random 128-bit number sent along with it.
+    pub fn begin_simplified(
+        &self,
+        email: &str,
+        a_pub: &BigUint,
+    ) -> anyhow::Result<SrpServerSession> {
+        let SrpParams { n, g, .. } = &self.params;
+        let user = self
+            .users
+            .get(email)
+            .ok_or_else(|| anyhow!("unknown user: {}", email))?;
+
+        let mut rng = rand::thread_rng();
+        let b = rng.gen_biguint_below(n);
+        let b_pub = modexp(g, &b, n);
+        let u = rng.gen_biguint(128);
+
+        // S = (A * v^u)^b mod N
+        let secret = modexp(&(a_pub * modexp(&user.verifier, &u, n)), &b, n);
+
+        Ok(SrpServerSession {
+            salt: user.salt.clone(),
+            b_pub,
+            u,
+            secret,
+        })
+    }
+}
+
+impl SrpServerSession {
+    /// Returns true if the client's proof matches the one derived from the server's secret.
+    pub fn verify(&self, proof: &[u8]) -> bool {
+        session_proof(&self.secret, &self.salt) == proof
+    }
+}
+
+/// An SRP client that knows its email and password.
+pub struct SrpClient {
+    params: SrpParams,
+    email: String,
+    password: String,
+    a: BigUint,
+}
+
+impl SrpClient {
+    pub fn new(params: SrpParams, email: &str, password: &str) -> Self {
+        let a = rand::thread_rng().gen_biguint_below(&params.n);
+        Self {
+            params,
+            email: email.to_string(),
+            password: password.to_string(),
+            a,
+        }
+    }
+
+    /// Returns the email and public key `A = g^a mod N` to send to the server.
+    pub fn hello(&self) -> (String, BigUint) {
+        (
+            self.email.clone(),
+            modexp(&self.params.g, &self.a, &self.params.n),
+        )
+    }
+
+    /// Handles the server's salt and public key `B`, returning the proof to send back.
+    pub fn proof(&self, salt: &[u8], b_pub: &BigUint) -> Vec<u8> {
+        let SrpParams { n, g, k } = &self.params;
+        let a_pub = modexp(g, &self.a, n);
+        let u = scrambler(&a_pub, b_pub);
+        let x = private_key(salt, &self.password);
+
+        // S = (B - k * g^x)^(a + u * x) mod N
+        let base = (b_pub + n - (k * modexp(g, &x, n)) % n) % n;
+        let secret = modexp(&base, &(&self.a + u * x), n);
+        session_proof(&secret, salt)
+    }
+
+    /// Like `proof`, but for simplified SRP, where the server sends `u` along with `B`.
+    pub fn proof_simplified(&self, salt: &[u8], b_pub: &BigUint, u: &BigUint) -> Vec<u8> {
+        let x = private_key(salt, &self.password);
+
+        // S = B^(a + u * x) mod N
+        let secret = modexp(b_pub, &(&self.a + u * x), &self.params.n);
+        session_proof(&secret, salt)
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn login(server: &SrpServer, client: &SrpClient) -> bool {
+        let (email, a_pub) = client.hello();
+        let session = server.begin(&email, &a_pub).unwrap();
+        session.verify(&client.proof(&session.salt, &session.b_pub))
+    }
+
+    #[test]
+    fn test_srp() {
+        let mut server = SrpServer::new(SrpParams::nist());
+        server.register("alice@example.com", "hunter2");
+
+        let client = SrpClient::new(SrpParams::nist(), "alice@example.com", "hunter2");
+        assert!(login(&server, &client));
+
+        let client = SrpClient::new(SrpParams::nist(), "alice@example.com", "hunter3");
+        assert!(!login(&server, &client));
+
+        let (_, a_pub) = client.hello();
+        assert!(server.begin("bob@example.com", &a_pub).is_err());
+    }
+
+    #[test]
+    fn test_simplified_srp() {
+        let mut server = SrpServer::new(SrpParams::nist());
+        server.register("alice@example.com", "hunter2");
+
+        for (password, ok) in [("hunter2", true), ("hunter3", false)] {
+            let client = SrpClient::new(SrpParams::nist(), "alice@example.com", password);
+            let (email, a_pub) = client.hello();
+            let session = server.begin_simplified(&email, &a_pub).unwrap();
+            let proof = client.proof_simplified(&session.salt, &session.b_pub, &session.u);
+            assert_eq!(session.verify(&proof), ok);
+        }
+    }
+
+    #[test]
+    fn test_zero_key_bypass() {
+        let params = SrpParams::nist();
+        let mut server = SrpServer::new(params.clone());
+        let mut hardened = SrpServer::new_hardened(params.clone());
+        server.register("alice@example.com", "hunter2");
+        hardened.register("alice@example.com", "hunter2");
+
+        // With A = 0 mod N, the server's secret is (A * v^u)^b = 0, so we can forge a proof
+        // without knowing the password.
+        for i in 0..3u32 {
+            let a_pub = &params.n * i;
+            let session = server.begin("alice@example.com", &a_pub).unwrap();
+            assert!(session.verify(&session_proof(&BigUint::zero(), &session.salt)));
+            assert!(hardened.begin("alice@example.com", &a_pub).is_err());
+        }
+
+        // Legit clients can still log in to the hardened server.
+        let client = SrpClient::new(params, "alice@example.com", "hunter2");
+        assert!(login(&hardened, &client));
+    }
+}